@@ -1,36 +1,165 @@
 #![no_std]
 
 use soroban_sdk::{
-    contract, contractimpl, contracttype, token, xdr::ScErrorCode, xdr::ScErrorType, Address,
-    Bytes, Env, Error, String,
+    contract, contracterror, contractimpl, contracttype, symbol_short, token, xdr::ToXdr,
+    Address, Bytes, BytesN, Env, IntoVal, String, TryFromVal, Val, Vec,
 };
 
 /// LockAndReleaseContract
 ///
 /// ### Trust Assumptions:
-/// - The contract owner is trusted to set the initial admin address once.
+/// - The contract owner is trusted to appoint and rotate the admin address.
 /// - The admin has full control over releasing funds on the destination chain.
 ///   Admin should be considered **fully trusted**, and should ideally be secured using
 ///   a multisig, hardware wallet, or MPC-based scheme.
 /// - The contract assumes the user provides a valid recipient address for the destination chain.
-/// - The contract does not validate destination chain or recipient address formats.
+/// - Destination chains must be registered by the owner, and recipients must match the
+///   chain's address format. The contract cannot check that the recipient exists or is
+///   controlled by the user.
 /// - No decentralized verification of destination transfers is enforced — assumes off-chain bridge layer.
 
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum LockReleaseError {
+    AlreadyInitialized = 1,
+    NotInitialized = 2,
+    AdminNotSet = 5,
+    InvalidAmount = 6,
+    AmountTooSmall = 7,
+    InsufficientAdminBalance = 8,
+    FeeTooHigh = 9,
+    InvalidThreshold = 10,
+    LockNotFound = 11,
+    AlreadyProcessed = 12,
+    InsufficientEscrowBalance = 13,
+    LockNotPending = 14,
+    RefundNotAvailable = 15,
+    InsufficientAdminAllowance = 16,
+    Unauthorized = 17,
+    ContractPaused = 18,
+    TokenPaused = 19,
+    ChainPaused = 20,
+    AlreadyMigrated = 21,
+    OwnershipRenounced = 22,
+    NoPendingOwner = 23,
+    NotRelayer = 24,
+    AlreadyApproved = 25,
+    RelayerApprovalRequired = 27,
+    ValidatorSetNotConfigured = 28,
+    StaleEpoch = 29,
+    InvalidPayload = 30,
+    UnknownValidator = 31,
+    DuplicateSignature = 32,
+    InsufficientSignatures = 33,
+    InvalidRecoveryId = 34,
+    BatchAlreadyPosted = 35,
+    BatchNotFound = 36,
+    InvalidProof = 37,
+    TokenNotSupported = 38,
+    ChainNotSupported = 39,
+    InvalidRecipient = 40,
+    RouteNotSupported = 41,
+    RateLimitExceeded = 42,
+    OutflowBreakerTripped = 43,
+    LockBelowMinimum = 44,
+    LockAboveMaximum = 45,
+    RevenueNotSet = 46,
+    EscrowNotEmpty = 47,
+    DuplicateValidator = 48,
+}
+
+/// Default protocol fee (3%) applied until the owner configures one.
+pub const DEFAULT_FEE_BPS: u32 = 300;
+/// Upper bound on the protocol fee (10%).
+pub const MAX_FEE_BPS: u32 = 1_000;
+const BPS_DENOMINATOR: i128 = 10_000;
+/// Current storage layout version, bumped whenever `migrate` gains a step.
+pub const STORAGE_VERSION: u32 = 1;
+/// Sweep threshold hardcoded before per-token thresholds (100 USDC, 6 decimals).
+const LEGACY_SWEEP_THRESHOLD: i128 = 100_000_000;
+/// Default time (7 days) after which an unfulfilled lock can be refunded.
+pub const DEFAULT_REFUND_EXPIRY: u64 = 7 * 24 * 60 * 60;
+/// Number of slots a rate limit window is tracked in.
+const RATE_LIMIT_SLOTS: u64 = 12;
+
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKey {
     Init,
     Owner,
-    AdminSet,
     Admin,
-    RevenueSet,
+    PendingAdmin, // Rotated admin waiting for the admin delay to pass
+    AdminDelay,   // Seconds before a rotated admin takes effect
+    PendingAdminDelay, // Lowered admin delay waiting for the current delay
     Revenue,
-    AccumulatedRevenue,
-    LockData(Address), // Stores LockData per user
+    AccumulatedRevenue(Address), // Unswept revenue per token
+    SweepThreshold(Address),     // Auto-sweep threshold per token
+    FeeConfig,
+    TokenFee(Address),         // Per-token FeeConfig override
+    LockNonce,                 // Next lock ID
+    Lock(u64),                 // Stores LockData per lock ID
+    Processed(SourceTransfer), // Marks an inbound transfer as released
+    CustodyMode(Address),      // Where locked funds of a token are held
+    EscrowBalance(Address),    // Locked funds held by the contract per token
+    RefundExpiry,              // Seconds before an unfulfilled lock can be refunded
+    Pauser,                    // Optional address allowed to pause alongside the owner
+    Paused,                    // Global pause flag
+    TokenPaused(Address),      // Per-token pause flag
+    ChainPaused(u64),          // Per-destination-chain pause flag
+    StorageVersion,            // Layout version of the keys above
+    PendingOwner,              // Proposed owner awaiting acceptance
+    Relayers,                  // Registered relayer set
+    RelayerThreshold,          // Approvals needed to release; 0 disables relayer mode
+    ReleaseApprovals(SourceTransfer, Address, Address, i128), // Relayer approvals for a release
+    ValidatorSet,              // Ed25519 validator keys for release_with_signatures
+    EvmValidatorSet,           // EVM signer addresses for release_with_evm_signatures
+    ReleaseRoot(u64),          // Merkle root of a batch of releases
+    Claimed(u64, BytesN<32>),  // Marks a batch leaf hash as claimed
+    TokenInfo(Address),        // Registry metadata of a lockable token
+    SupportedTokens,           // Addresses of all registered tokens
+    ChainInfo(u64),            // Registry metadata of a destination chain
+    SupportedChains,           // IDs of all registered destination chains
+    Route(Address, u64),       // Destination tokens allowed for a source token and chain
+    RouteChains(Address),      // Chains with at least one route for a source token
+    RateLimit(Address, RateLimitKind), // Volume limit for a token
+    TokenRateUsage(Address, RateLimitKind), // Recent usage slots of a token limit
+    UserRateUsage(Address, Address), // Recent lock usage slots of a user and token
+    OutflowBreaker(Address),   // Token-bucket parameters of a token's release breaker
+    OutflowBucket(Address),    // Current token-bucket level of a token
+    OutflowTripped(Address),   // Set when a release burst tripped the breaker
+    LockLimits(Address),       // Minimum and maximum in_amount per lock of a token
 }
 
+/// Keys of the version 0 storage layout, kept only so `migrate` can read
+/// them.
 #[derive(Clone)]
 #[contracttype]
+pub enum LegacyDataKey {
+    AdminSet,           // One-shot flag for set_admin
+    RevenueSet,         // One-shot flag for set_revenue_address
+    AccumulatedRevenue, // Unswept revenue shared by all tokens
+    LockData(Address),  // Last lock per user
+}
+
+/// Where `lock` keeps a token's locked funds and `release` pays them from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum CustodyMode {
+    ForwardToAdmin, // Forwarded to the admin wallet (default)
+    Escrow,         // Held by the contract
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum LockStatus {
+    Pending,
+    Fulfilled,
+    Refunded,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
 pub struct LockData {
     pub user_address: Address,
     pub dest_token: String,
@@ -38,8 +167,212 @@ pub struct LockData {
     pub src_token: Address,
     pub in_amount: i128,
     pub swaped_amount: i128,
-    pub recipient_address: String,
-    pub dest_chain: Bytes,
+    pub recipient_address: Recipient,
+    pub dest_chain: u64,
+    pub timestamp: u64,       // Ledger timestamp of the lock
+    pub custody: CustodyMode, // Where swaped_amount was placed
+    pub status: LockStatus,
+}
+
+/// Registry entry for a token that `lock` accepts as `from_token`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct TokenInfo {
+    pub decimals: u32,
+    pub symbol: String,
+    pub enabled: bool, // Disabled tokens stay registered but cannot be locked
+}
+
+/// Recipient address format used by a destination chain.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum AddressFormat {
+    Evm,     // 20-byte hex address
+    Stellar, // Stellar account or contract address
+    Raw,     // Chain-specific bytes
+}
+
+/// Recipient of a lock on the destination chain.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum Recipient {
+    Evm(BytesN<20>),
+    Stellar(Address),
+    Raw(Bytes),
+}
+
+/// Registry entry for a chain that `lock` accepts as `dest_chain`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ChainInfo {
+    pub name: String,
+    pub address_format: AddressFormat,
+    pub enabled: bool, // Disabled chains stay registered but cannot be locked to
+}
+
+/// Destination tokens that a source token can be bridged to on one chain.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Route {
+    pub dest_chain: u64,
+    pub dest_tokens: Vec<String>,
+}
+
+/// Volume that a token rate limit applies to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum RateLimitKind {
+    UserLock,     // Amount each user locks
+    TokenLock,    // Amount all users lock
+    TokenRelease, // Amount released
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct RateLimit {
+    pub max_amount: i128, // Most volume allowed per window
+    pub window: u64,      // Window length in seconds
+}
+
+/// Volume counted against a rate limit from transfers made before `end`,
+/// in the slot that ends there.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct RateSlot {
+    pub end: u64,
+    pub amount: i128,
+}
+
+/// Bounds on the `in_amount` of a single lock of a token.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct LockLimits {
+    pub min_amount: i128,
+    pub max_amount: i128,
+}
+
+/// Token bucket that releases of a token draw from. The bucket holds up to
+/// `capacity` and refills by `refill_per_second`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct OutflowBreaker {
+    pub capacity: i128,
+    pub refill_per_second: i128,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct OutflowBucket {
+    pub level: i128,
+    pub updated_at: u64, // Ledger timestamp of the last refill
+}
+
+/// Identifies an inbound transfer on its source chain, so each one is
+/// released at most once.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct SourceTransfer {
+    pub chain_id: u64,
+    pub transfer_hash: BytesN<32>, // Source tx hash or bridge nonce
+}
+
+/// A release awaiting relayer approvals, keyed by its source transfer, user,
+/// token and amount, so conflicting claims are counted separately.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ReleaseRequest {
+    pub user: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub approvals: Vec<Address>,
+}
+
+/// Off-chain validators whose ed25519 signatures authorize releases. The
+/// epoch increases every time the owner replaces the set.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ValidatorSet {
+    pub epoch: u32,
+    pub public_keys: Vec<BytesN<32>>,
+    pub threshold: u32,
+}
+
+/// Release signed by validators. The signed message is the XDR encoding of
+/// this struct, which binds it to one contract and one validator epoch.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ReleasePayload {
+    pub contract: Address,
+    pub epoch: u32,
+    pub user: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub source_transfer: SourceTransfer,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ValidatorSignature {
+    pub public_key: BytesN<32>,
+    pub signature: BytesN<64>,
+}
+
+/// Secp256k1 validators identified by their 20-byte EVM addresses, so the
+/// keys that sign for `Swapper.sol` can also sign Stellar releases.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct EvmValidatorSet {
+    pub epoch: u32,
+    pub signers: Vec<BytesN<20>>,
+    pub threshold: u32,
+}
+
+/// ECDSA signature split as `r || s` plus the recovery ID (`v`, either 0/1
+/// or 27/28).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct EvmSignature {
+    pub signature: BytesN<64>,
+    pub recovery_id: u32,
+}
+
+/// One release in a batch posted with `post_release_root`. Leaves are
+/// hashed as `sha256(XDR(leaf))`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ReleaseLeaf {
+    pub user: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub source_transfer: SourceTransfer,
+}
+
+/// Body of the event published by `lock`. Topics are
+/// `("Lock", user_address, from_token)`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct LockEvent {
+    pub lock_id: u64,
+    pub user_address: Address,
+    pub from_token: Address,
+    pub src_token: Address,
+    pub dest_token: String,
+    pub in_amount: i128,
+    pub swaped_amount: i128,
+    pub recipient_address: Recipient,
+    pub dest_chain: u64,
+}
+
+/// Body of the event published by `release`, mirroring `Release` on the
+/// EVM `Swapper.sol`. Topics are `("Release", user)`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct ReleaseEvent {
+    pub user: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub admin: Address,
+    pub source_transfer: SourceTransfer,
 }
 
 #[derive(Clone)]
@@ -48,85 +381,1452 @@ pub struct AdminData {
     pub admin_address: Address,
 }
 
+/// An admin rotation scheduled by `set_admin` while an admin delay is set.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct PendingAdmin {
+    pub admin_address: Address,
+    pub effective_at: u64, // Ledger timestamp from which the new admin is active
+}
+
+/// A lowered admin delay, scheduled by `set_admin_delay` to take effect
+/// once the current delay has passed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct PendingAdminDelay {
+    pub delay: u64,
+    pub effective_at: u64, // Ledger timestamp from which the new delay applies
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub struct RevenueData {
     pub revenue_address: Address,
 }
 
-#[contract]
-pub struct LockAndReleaseContract;
+#[derive(Clone)]
+#[contracttype]
+pub struct FeeConfig {
+    pub fee_bps: u32,
+}
+
+fn get_owner(env: &Env) -> Result<Address, LockReleaseError> {
+    let storage = env.storage().instance();
+    storage.get(&DataKey::Owner).ok_or(if storage.has(&DataKey::Init) {
+        LockReleaseError::OwnershipRenounced
+    } else {
+        LockReleaseError::NotInitialized
+    })
+}
+
+/// Returns the storage layout version. Deployments that predate versioning
+/// have no `StorageVersion` entry and report 0.
+fn get_storage_version(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::StorageVersion)
+        .unwrap_or(0)
+}
+
+/// Returns the active admin: a pending rotation once its delay has passed,
+/// otherwise the stored admin.
+fn get_admin(env: &Env) -> Result<Address, LockReleaseError> {
+    let pending: Option<PendingAdmin> = env.storage().instance().get(&DataKey::PendingAdmin);
+    if let Some(pending) = pending {
+        if env.ledger().timestamp() >= pending.effective_at {
+            return Ok(pending.admin_address);
+        }
+    }
+    let admin_data: AdminData = env
+        .storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .ok_or(LockReleaseError::AdminNotSet)?;
+    Ok(admin_data.admin_address)
+}
+
+fn get_admin_delay(env: &Env) -> u64 {
+    let pending: Option<PendingAdminDelay> =
+        env.storage().instance().get(&DataKey::PendingAdminDelay);
+    if let Some(pending) = pending {
+        if env.ledger().timestamp() >= pending.effective_at {
+            return pending.delay;
+        }
+    }
+    env.storage().instance().get(&DataKey::AdminDelay).unwrap_or(0)
+}
+
+fn get_fee_bps(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get::<_, FeeConfig>(&DataKey::FeeConfig)
+        .map(|config| config.fee_bps)
+        .unwrap_or(DEFAULT_FEE_BPS)
+}
+
+fn get_token_fee_bps(env: &Env, token: &Address) -> u32 {
+    env.storage()
+        .instance()
+        .get::<_, FeeConfig>(&DataKey::TokenFee(token.clone()))
+        .map(|config| config.fee_bps)
+        .unwrap_or_else(|| get_fee_bps(env))
+}
+
+fn get_custody_mode(env: &Env, token: &Address) -> CustodyMode {
+    env.storage()
+        .instance()
+        .get(&DataKey::CustodyMode(token.clone()))
+        .unwrap_or(CustodyMode::ForwardToAdmin)
+}
+
+fn get_escrow_balance(env: &Env, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::EscrowBalance(token.clone()))
+        .unwrap_or(0)
+}
+
+fn set_escrow_balance(env: &Env, token: &Address, balance: i128) {
+    env.storage()
+        .instance()
+        .set(&DataKey::EscrowBalance(token.clone()), &balance);
+}
+
+fn get_refund_expiry(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::RefundExpiry)
+        .unwrap_or(DEFAULT_REFUND_EXPIRY)
+}
+
+/// Authenticates `caller` as either the owner or the pauser.
+fn require_owner_or_pauser(env: &Env, caller: &Address) -> Result<(), LockReleaseError> {
+    let owner = get_owner(env).ok();
+    let pauser: Option<Address> = env.storage().instance().get(&DataKey::Pauser);
+    if owner.as_ref() != Some(caller) && pauser.as_ref() != Some(caller) {
+        return Err(LockReleaseError::Unauthorized);
+    }
+    caller.require_auth();
+    Ok(())
+}
+
+fn is_flag_set(env: &Env, key: &DataKey) -> bool {
+    env.storage().instance().get(key).unwrap_or(false)
+}
+
+fn require_token_active(env: &Env, token: &Address) -> Result<(), LockReleaseError> {
+    if is_flag_set(env, &DataKey::Paused) {
+        return Err(LockReleaseError::ContractPaused);
+    }
+    if is_flag_set(env, &DataKey::TokenPaused(token.clone())) {
+        return Err(LockReleaseError::TokenPaused);
+    }
+    Ok(())
+}
+
+fn get_supported_tokens(env: &Env) -> Vec<Address> {
+    env.storage()
+        .instance()
+        .get(&DataKey::SupportedTokens)
+        .unwrap_or(Vec::new(env))
+}
+
+fn require_token_supported(env: &Env, token: &Address) -> Result<(), LockReleaseError> {
+    let info: Option<TokenInfo> = env
+        .storage()
+        .instance()
+        .get(&DataKey::TokenInfo(token.clone()));
+    match info {
+        Some(info) if info.enabled => Ok(()),
+        _ => Err(LockReleaseError::TokenNotSupported),
+    }
+}
+
+fn get_supported_chains(env: &Env) -> Vec<u64> {
+    env.storage()
+        .instance()
+        .get(&DataKey::SupportedChains)
+        .unwrap_or(Vec::new(env))
+}
+
+fn get_active_chain(env: &Env, chain_id: u64) -> Result<ChainInfo, LockReleaseError> {
+    let info: Option<ChainInfo> = env.storage().instance().get(&DataKey::ChainInfo(chain_id));
+    match info {
+        Some(info) if info.enabled => Ok(info),
+        _ => Err(LockReleaseError::ChainNotSupported),
+    }
+}
+
+fn hex_value(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Parses an ASCII EVM address, with or without a `0x` prefix.
+fn parse_evm_address(env: &Env, hex: &Bytes) -> Result<BytesN<20>, LockReleaseError> {
+    let offset = match hex.len() {
+        40 => 0,
+        42 if hex.get(0) == Some(b'0') && matches!(hex.get(1), Some(b'x' | b'X')) => 2,
+        _ => return Err(LockReleaseError::InvalidRecipient),
+    };
+    let mut address = [0u8; 20];
+    for (i, byte) in address.iter_mut().enumerate() {
+        let pos = offset + 2 * i as u32;
+        let high = hex.get(pos).and_then(hex_value);
+        let low = hex.get(pos + 1).and_then(hex_value);
+        match (high, low) {
+            (Some(high), Some(low)) => *byte = (high << 4) | low,
+            _ => return Err(LockReleaseError::InvalidRecipient),
+        }
+    }
+    Ok(BytesN::from_array(env, &address))
+}
+
+/// Checks `recipient` against the destination chain's address format.
+/// Hex strings sent as `Raw` to an EVM chain are normalized to `Evm`.
+fn validate_recipient(
+    env: &Env,
+    format: AddressFormat,
+    recipient: Recipient,
+) -> Result<Recipient, LockReleaseError> {
+    match (format, recipient) {
+        (AddressFormat::Evm, Recipient::Evm(address)) => Ok(Recipient::Evm(address)),
+        (AddressFormat::Evm, Recipient::Raw(hex)) => {
+            Ok(Recipient::Evm(parse_evm_address(env, &hex)?))
+        }
+        (AddressFormat::Stellar, Recipient::Stellar(address)) => Ok(Recipient::Stellar(address)),
+        (AddressFormat::Raw, Recipient::Raw(bytes)) if !bytes.is_empty() => {
+            Ok(Recipient::Raw(bytes))
+        }
+        _ => Err(LockReleaseError::InvalidRecipient),
+    }
+}
+
+fn get_route(env: &Env, from_token: &Address, dest_chain: u64) -> Vec<String> {
+    env.storage()
+        .instance()
+        .get(&DataKey::Route(from_token.clone(), dest_chain))
+        .unwrap_or(Vec::new(env))
+}
+
+fn get_route_chains(env: &Env, from_token: &Address) -> Vec<u64> {
+    env.storage()
+        .instance()
+        .get(&DataKey::RouteChains(from_token.clone()))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Adds `amount` to the usage stored under `usage_key`, failing if that
+/// exceeds the `kind` limit of `token` over the last `window` seconds.
+/// Usage is kept in slots of `window / RATE_LIMIT_SLOTS`, and a slot counts
+/// in full while any part of it is inside the window, so the limit is a
+/// hard bound that may reject up to one slot early.
+fn consume_rate_limit(
+    env: &Env,
+    token: &Address,
+    kind: RateLimitKind,
+    usage_key: DataKey,
+    amount: i128,
+) -> Result<(), LockReleaseError> {
+    let limit: RateLimit = match env
+        .storage()
+        .instance()
+        .get(&DataKey::RateLimit(token.clone(), kind))
+    {
+        Some(limit) => limit,
+        None => return Ok(()),
+    };
+
+    let now = env.ledger().timestamp();
+    let slot_length = limit.window.div_ceil(RATE_LIMIT_SLOTS);
+    let current_end = (now - now % slot_length).saturating_add(slot_length);
+    let window_start = now.saturating_sub(limit.window);
+
+    // Drop slots that ended before the window, and total the rest
+    let stored: Vec<RateSlot> = env
+        .storage()
+        .persistent()
+        .get(&usage_key)
+        .unwrap_or(Vec::new(env));
+    let mut slots = Vec::new(env);
+    let mut used: i128 = 0;
+    for slot in stored.iter() {
+        if slot.end > window_start {
+            used = used.saturating_add(slot.amount);
+            slots.push_back(slot);
+        }
+    }
+    if used.saturating_add(amount) > limit.max_amount {
+        return Err(LockReleaseError::RateLimitExceeded);
+    }
+
+    match slots.last() {
+        Some(mut slot) if slot.end == current_end => {
+            slot.amount += amount;
+            slots.set(slots.len() - 1, slot);
+        }
+        _ => slots.push_back(RateSlot {
+            end: current_end,
+            amount,
+        }),
+    }
+    env.storage().persistent().set(&usage_key, &slots);
+    Ok(())
+}
+
+/// Draws `amount` from the outflow bucket of `token`. If the bucket cannot
+/// cover it, trips the breaker and returns false.
+fn consume_outflow_bucket(env: &Env, token: &Address, amount: i128) -> bool {
+    let breaker: OutflowBreaker = match env
+        .storage()
+        .instance()
+        .get(&DataKey::OutflowBreaker(token.clone()))
+    {
+        Some(breaker) => breaker,
+        None => return true,
+    };
+
+    let now = env.ledger().timestamp();
+    let bucket_key = DataKey::OutflowBucket(token.clone());
+    let bucket = env
+        .storage()
+        .instance()
+        .get(&bucket_key)
+        .unwrap_or(OutflowBucket {
+            level: breaker.capacity,
+            updated_at: now,
+        });
+    let refill = ((now - bucket.updated_at) as i128).saturating_mul(breaker.refill_per_second);
+    let available = bucket.level.saturating_add(refill).min(breaker.capacity);
+
+    if amount > available {
+        env.storage()
+            .instance()
+            .set(&DataKey::OutflowTripped(token.clone()), &true);
+        env.events()
+            .publish(("AnomalyDetected", token.clone()), (amount, available));
+        return false;
+    }
+    env.storage().instance().set(
+        &bucket_key,
+        &OutflowBucket {
+            level: available - amount,
+            updated_at: now,
+        },
+    );
+    true
+}
+
+fn get_relayers(env: &Env) -> Vec<Address> {
+    env.storage()
+        .instance()
+        .get(&DataKey::Relayers)
+        .unwrap_or(Vec::new(env))
+}
+
+fn get_relayer_threshold(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::RelayerThreshold)
+        .unwrap_or(0)
+}
+
+/// Pays out an inbound transfer exactly once. Escrowed tokens are paid by
+/// the contract. Otherwise the admin pays, either directly when it signed
+/// the call, or through the allowance it granted to this contract.
+///
+/// Returns false without paying if the release tripped the token's outflow
+/// breaker. The call still succeeds so that the trip is persisted, and the
+/// transfer stays unprocessed so it can be released once the owner resets
+/// the breaker.
+fn execute_release(
+    env: &Env,
+    user: Address,
+    token: Address,
+    amount: i128,
+    source_transfer: SourceTransfer,
+    admin_signed: bool,
+) -> Result<bool, LockReleaseError> {
+    let admin = get_admin(env)?;
+
+    // Ensure releases are not paused for this token
+    require_token_active(env, &token)?;
+    if is_flag_set(env, &DataKey::OutflowTripped(token.clone())) {
+        return Err(LockReleaseError::OutflowBreakerTripped);
+    }
+
+    // Validate amount
+    if amount < 1 {
+        return Err(LockReleaseError::InvalidAmount);
+    }
+
+    // Each inbound transfer can only be released once
+    let processed_key = DataKey::Processed(source_transfer.clone());
+    if env.storage().persistent().has(&processed_key) {
+        return Err(LockReleaseError::AlreadyProcessed);
+    }
+
+    // Trip the breaker on bursts beyond the token's outflow bucket
+    if !consume_outflow_bucket(env, &token, amount) {
+        return Ok(false);
+    }
+
+    // Enforce the token's outflow limit
+    consume_rate_limit(
+        env,
+        &token,
+        RateLimitKind::TokenRelease,
+        DataKey::TokenRateUsage(token.clone(), RateLimitKind::TokenRelease),
+        amount,
+    )?;
+
+    // Pay from escrow or from the admin's balance
+    let token_client = token::Client::new(env, &token);
+    let contract = env.current_contract_address();
+    match get_custody_mode(env, &token) {
+        CustodyMode::Escrow => {
+            let escrow_balance = get_escrow_balance(env, &token);
+            if escrow_balance < amount {
+                return Err(LockReleaseError::InsufficientEscrowBalance);
+            }
+
+            // Mark as processed, then perform token release to the user
+            env.storage().persistent().set(&processed_key, &());
+            set_escrow_balance(env, &token, escrow_balance - amount);
+            token_client.transfer(&contract, &user, &amount);
+        }
+        CustodyMode::ForwardToAdmin => {
+            // Check admin's balance
+            if token_client.balance(&admin) < amount {
+                return Err(LockReleaseError::InsufficientAdminBalance);
+            }
+            if !admin_signed && token_client.allowance(&admin, &contract) < amount {
+                return Err(LockReleaseError::InsufficientAdminAllowance);
+            }
+
+            // Mark as processed, then perform token release to the user
+            env.storage().persistent().set(&processed_key, &());
+            if admin_signed {
+                token_client.transfer(&admin, &user, &amount);
+            } else {
+                token_client.transfer_from(&contract, &admin, &user, &amount);
+            }
+        }
+    }
+
+    // Emit release event
+    env.events().publish(
+        (symbol_short!("Release"), user.clone()),
+        ReleaseEvent {
+            user,
+            token,
+            amount,
+            admin,
+            source_transfer,
+        },
+    );
+    Ok(true)
+}
+
+/// Rejects a signed payload made for another contract or validator epoch.
+fn check_release_payload(
+    env: &Env,
+    payload: &ReleasePayload,
+    epoch: u32,
+) -> Result<(), LockReleaseError> {
+    if payload.epoch != epoch {
+        return Err(LockReleaseError::StaleEpoch);
+    }
+    if payload.contract != env.current_contract_address() {
+        return Err(LockReleaseError::InvalidPayload);
+    }
+    Ok(())
+}
+
+/// Recovers the EVM address that signed `payload`. Validators sign the
+/// keccak256 digest of the payload's XDR encoding as an EIP-191 personal
+/// message, the same way they sign messages for `Swapper.sol`.
+fn recover_evm_signer(
+    env: &Env,
+    payload: &ReleasePayload,
+    signature: &EvmSignature,
+) -> Result<BytesN<20>, LockReleaseError> {
+    let recovery_id = match signature.recovery_id {
+        0 | 1 => signature.recovery_id,
+        27 | 28 => signature.recovery_id - 27,
+        _ => return Err(LockReleaseError::InvalidRecoveryId),
+    };
+
+    let payload_hash = env.crypto().keccak256(&payload.clone().to_xdr(env));
+    let mut message = Bytes::from_slice(env, b"\x19Ethereum Signed Message:\n32");
+    message.append(&payload_hash.to_bytes().into());
+    let digest = env.crypto().keccak256(&message);
+
+    // The address is the last 20 bytes of keccak256(x || y)
+    let public_key = env
+        .crypto()
+        .secp256k1_recover(&digest, &signature.signature, recovery_id);
+    let key_hash = env
+        .crypto()
+        .keccak256(&Bytes::from(public_key).slice(1..))
+        .to_array();
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&key_hash[12..]);
+    Ok(BytesN::from_array(env, &address))
+}
+
+fn hash_release_leaf(env: &Env, leaf: &ReleaseLeaf) -> BytesN<32> {
+    env.crypto().sha256(&leaf.clone().to_xdr(env)).to_bytes()
+}
+
+/// Folds `proof` into `leaf_hash`, hashing each pair in sorted order so
+/// proofs need no left/right flags.
+fn compute_merkle_root(env: &Env, leaf_hash: BytesN<32>, proof: &Vec<BytesN<32>>) -> BytesN<32> {
+    let mut node = leaf_hash;
+    for sibling in proof.iter() {
+        let (first, second) = if node <= sibling {
+            (node, sibling)
+        } else {
+            (sibling, node)
+        };
+        let mut pair = Bytes::from(first);
+        pair.append(&Bytes::from(second));
+        node = env.crypto().sha256(&pair).to_bytes();
+    }
+    node
+}
+
+fn has_duplicates<T>(items: &Vec<T>) -> bool
+where
+    T: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+{
+    (1..items.len()).any(|i| items.slice(..i).contains(items.get_unchecked(i)))
+}
+
+fn get_lock(env: &Env, lock_id: u64) -> Result<LockData, LockReleaseError> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::Lock(lock_id))
+        .ok_or(LockReleaseError::LockNotFound)
+}
+
+#[contract]
+pub struct LockAndReleaseContract;
+
+#[contractimpl]
+impl LockAndReleaseContract {
+    pub fn initialize(env: Env, owner: Address) -> Result<(), LockReleaseError> {
+        // Prevent re-initialization
+        if env.storage().instance().has(&DataKey::Init) {
+            return Err(LockReleaseError::AlreadyInitialized);
+        }
+
+        // Authenticate the caller as the owner
+        owner.require_auth();
+
+        // Set the contract owner and mark as initialized
+        env.storage().instance().set(&DataKey::Owner, &owner);
+        env.storage().instance().set(&DataKey::Init, &());
+        env.storage()
+            .instance()
+            .set(&DataKey::StorageVersion, &STORAGE_VERSION);
+        Ok(())
+    }
+
+    pub fn get_owner(env: Env) -> Result<Address, LockReleaseError> {
+        get_owner(&env)
+    }
+
+    pub fn get_pending_owner(env: Env) -> Option<Address> {
+        env.storage().instance().get(&DataKey::PendingOwner)
+    }
+
+    /// Starts a two-step ownership transfer. `new_owner` becomes the owner
+    /// once it calls `accept_ownership`.
+    pub fn propose_owner(env: Env, new_owner: Address) -> Result<(), LockReleaseError> {
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        env.storage().instance().set(&DataKey::PendingOwner, &new_owner);
+
+        // Emit event for transparency
+        env.events()
+            .publish(("OwnershipTransferProposed", owner, new_owner), 1);
+        Ok(())
+    }
+
+    pub fn accept_ownership(env: Env) -> Result<(), LockReleaseError> {
+        let new_owner: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::PendingOwner)
+            .ok_or(LockReleaseError::NoPendingOwner)?;
+        new_owner.require_auth();
+
+        let old_owner = get_owner(&env)?;
+        env.storage().instance().set(&DataKey::Owner, &new_owner);
+        env.storage().instance().remove(&DataKey::PendingOwner);
+
+        // Emit event for transparency
+        env.events()
+            .publish(("OwnershipTransferred", old_owner, new_owner), 1);
+        Ok(())
+    }
+
+    pub fn cancel_ownership_transfer(env: Env) -> Result<(), LockReleaseError> {
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let pending_owner: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::PendingOwner)
+            .ok_or(LockReleaseError::NoPendingOwner)?;
+        env.storage().instance().remove(&DataKey::PendingOwner);
+
+        // Emit event for transparency
+        env.events()
+            .publish(("OwnershipTransferCancelled", owner, pending_owner), 1);
+        Ok(())
+    }
+
+    /// Permanently gives up ownership. Every owner-only function fails with
+    /// `OwnershipRenounced` afterwards.
+    pub fn renounce_ownership(env: Env) -> Result<(), LockReleaseError> {
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        env.storage().instance().remove(&DataKey::Owner);
+        env.storage().instance().remove(&DataKey::PendingOwner);
+
+        // Emit event for transparency
+        env.events().publish(("OwnershipRenounced", owner), 1);
+        Ok(())
+    }
+
+    /// Replaces the contract code, keeping its address and storage. Call
+    /// `migrate` afterwards if the new code bumps `STORAGE_VERSION`.
+    pub fn upgrade(env: Env, new_wasm_hash: BytesN<32>) -> Result<(), LockReleaseError> {
+        // Only the owner can upgrade the contract
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        env.deployer()
+            .update_current_contract_wasm(new_wasm_hash.clone());
+
+        // Emit event for transparency
+        env.events().publish(("UpgradeEvent",), new_wasm_hash);
+        Ok(())
+    }
+
+    /// Upgrades storage written by older contract versions to the current
+    /// layout.
+    ///
+    /// Version 0 kept a single `AccumulatedRevenue` for every token and swept
+    /// it at a fixed 100 USDC. That balance is moved to
+    /// `AccumulatedRevenue(legacy_revenue_token)`, which also gets the old
+    /// threshold. The one-shot `AdminSet`/`RevenueSet` flags are dropped.
+    /// Per-user `LockData(Address)` records cannot be enumerated and are
+    /// left in place.
+    pub fn migrate(env: Env, legacy_revenue_token: Address) -> Result<(), LockReleaseError> {
+        // Only the owner can migrate storage
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let version = get_storage_version(&env);
+        if version >= STORAGE_VERSION {
+            return Err(LockReleaseError::AlreadyMigrated);
+        }
+
+        if version < 1 {
+            let storage = env.storage().instance();
+            let legacy_revenue: i128 = storage
+                .get(&LegacyDataKey::AccumulatedRevenue)
+                .unwrap_or(0);
+            storage.remove(&LegacyDataKey::AccumulatedRevenue);
+
+            let revenue_key = DataKey::AccumulatedRevenue(legacy_revenue_token.clone());
+            let accumulated_revenue: i128 = storage.get(&revenue_key).unwrap_or(0);
+            storage.set(&revenue_key, &(accumulated_revenue + legacy_revenue));
+
+            let threshold_key = DataKey::SweepThreshold(legacy_revenue_token);
+            if !storage.has(&threshold_key) {
+                storage.set(&threshold_key, &LEGACY_SWEEP_THRESHOLD);
+            }
+
+            // Admin and revenue address are no longer one-shot
+            storage.remove(&LegacyDataKey::AdminSet);
+            storage.remove(&LegacyDataKey::RevenueSet);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::StorageVersion, &STORAGE_VERSION);
+
+        // Emit event for transparency
+        env.events()
+            .publish(("MigrateEvent",), (version, STORAGE_VERSION));
+        Ok(())
+    }
+
+    pub fn get_storage_version(env: Env) -> u32 {
+        get_storage_version(&env)
+    }
+
+    /// Sets or rotates the admin. The first admin takes effect immediately;
+    /// later rotations wait for the admin delay, if one is configured.
+    pub fn set_admin(env: Env, admin: Address) -> Result<(), LockReleaseError> {
+        // Only the owner can set the admin
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        // Settle any rotation whose delay has already passed
+        let old_admin = get_admin(&env).ok();
+        if let Some(old_admin) = &old_admin {
+            env.storage().instance().set(&DataKey::Admin, &AdminData {
+                admin_address: old_admin.clone(),
+            });
+        }
+        env.storage().instance().remove(&DataKey::PendingAdmin);
+
+        let delay = get_admin_delay(&env);
+        let now = env.ledger().timestamp();
+        // The first admin, or any admin without a delay, applies immediately
+        let applies_now = old_admin.is_none() || delay == 0;
+        let effective_at = if applies_now { now } else { now.saturating_add(delay) };
+        if applies_now {
+            env.storage().instance().set(&DataKey::Admin, &AdminData {
+                admin_address: admin.clone(),
+            });
+        } else {
+            env.storage().instance().set(&DataKey::PendingAdmin, &PendingAdmin {
+                admin_address: admin.clone(),
+                effective_at,
+            });
+        }
+
+        // Emit event for transparency
+        let topics = ("AdminSetEvent", admin);
+        env.events().publish(topics, (old_admin, effective_at));
+        Ok(())
+    }
+
+    pub fn get_admin(env: Env) -> Result<Address, LockReleaseError> {
+        get_admin(&env)
+    }
+
+    /// Returns the admin rotation waiting for its delay to pass, if any.
+    pub fn get_pending_admin(env: Env) -> Option<PendingAdmin> {
+        env.storage()
+            .instance()
+            .get::<_, PendingAdmin>(&DataKey::PendingAdmin)
+            .filter(|pending| env.ledger().timestamp() < pending.effective_at)
+    }
+
+    /// Sets the delay before a rotated admin takes effect. Raising the delay
+    /// applies immediately; lowering it only applies once the current delay
+    /// has passed, so it cannot be used to skip the delay.
+    pub fn set_admin_delay(env: Env, delay: u64) -> Result<(), LockReleaseError> {
+        // Only the owner can change the admin delay
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let current_delay = get_admin_delay(&env);
+        let now = env.ledger().timestamp();
+        let effective_at = if delay >= current_delay {
+            env.storage().instance().set(&DataKey::AdminDelay, &delay);
+            env.storage().instance().remove(&DataKey::PendingAdminDelay);
+            now
+        } else {
+            let effective_at = now.saturating_add(current_delay);
+            env.storage().instance().set(&DataKey::AdminDelay, &current_delay);
+            env.storage().instance().set(&DataKey::PendingAdminDelay, &PendingAdminDelay {
+                delay,
+                effective_at,
+            });
+            effective_at
+        };
+
+        // Emit event for transparency
+        env.events().publish(("AdminDelayUpdated",), (delay, effective_at));
+        Ok(())
+    }
+
+    pub fn get_admin_delay(env: Env) -> u64 {
+        get_admin_delay(&env)
+    }
+
+    /// Returns the lowered admin delay waiting to take effect, if any.
+    pub fn get_pending_admin_delay(env: Env) -> Option<PendingAdminDelay> {
+        env.storage()
+            .instance()
+            .get::<_, PendingAdminDelay>(&DataKey::PendingAdminDelay)
+            .filter(|pending| env.ledger().timestamp() < pending.effective_at)
+    }
+
+    pub fn set_revenue_address(env: Env, revenue_address: Address) -> Result<(), LockReleaseError> {
+        // Only the owner can set the revenue address
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let old_revenue: Option<RevenueData> = env.storage().instance().get(&DataKey::Revenue);
+
+        // Set revenue address
+        env.storage().instance().set(&DataKey::Revenue, &RevenueData {
+            revenue_address: revenue_address.clone(),
+        });
+
+        // Emit event for transparency
+        let topics = ("RevenueAddressSetEvent", revenue_address);
+        env.events()
+            .publish(topics, old_revenue.map(|revenue| revenue.revenue_address));
+        Ok(())
+    }
+
+    pub fn set_fee_bps(env: Env, fee_bps: u32) -> Result<(), LockReleaseError> {
+        // Only the owner can change the fee
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        if fee_bps > MAX_FEE_BPS {
+            return Err(LockReleaseError::FeeTooHigh);
+        }
+
+        let old_fee_bps = get_fee_bps(&env);
+        env.storage()
+            .instance()
+            .set(&DataKey::FeeConfig, &FeeConfig { fee_bps });
+
+        // Emit event for transparency
+        env.events()
+            .publish(("FeeUpdated",), (old_fee_bps, fee_bps));
+        Ok(())
+    }
+
+    pub fn get_fee_bps(env: Env) -> u32 {
+        get_fee_bps(&env)
+    }
+
+    pub fn set_token_fee_bps(
+        env: Env,
+        token: Address,
+        fee_bps: u32,
+    ) -> Result<(), LockReleaseError> {
+        // Only the owner can change the fee
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        if fee_bps > MAX_FEE_BPS {
+            return Err(LockReleaseError::FeeTooHigh);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::TokenFee(token.clone()), &FeeConfig { fee_bps });
+
+        // Emit event for transparency
+        env.events().publish(("TokenFeeUpdated", token), fee_bps);
+        Ok(())
+    }
+
+    pub fn clear_token_fee_bps(env: Env, token: Address) -> Result<(), LockReleaseError> {
+        // Only the owner can change the fee
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        env.storage()
+            .instance()
+            .remove(&DataKey::TokenFee(token.clone()));
+
+        // Emit event for transparency
+        env.events().publish(("TokenFeeCleared", token), 1);
+        Ok(())
+    }
+
+    /// Returns the fee `lock` will charge on `token`: its override if one
+    /// is set, otherwise the global fee.
+    pub fn get_token_fee_bps(env: Env, token: Address) -> u32 {
+        get_token_fee_bps(&env, &token)
+    }
+
+    pub fn set_sweep_threshold(
+        env: Env,
+        token: Address,
+        threshold: i128,
+    ) -> Result<(), LockReleaseError> {
+        // Only the owner can change the sweep threshold
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        if threshold < 1 {
+            return Err(LockReleaseError::InvalidThreshold);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::SweepThreshold(token.clone()), &threshold);
+
+        // Emit event for transparency
+        env.events().publish(("SweepThresholdUpdated", token), threshold);
+        Ok(())
+    }
+
+    /// Returns the sweep threshold for `token`, or `None` if revenue in that
+    /// token is never swept automatically.
+    pub fn get_sweep_threshold(env: Env, token: Address) -> Option<i128> {
+        env.storage().instance().get(&DataKey::SweepThreshold(token))
+    }
+
+    /// Pays all accumulated revenue in `token` to the revenue address,
+    /// regardless of the sweep threshold. Returns the amount swept.
+    pub fn sweep_revenue(env: Env, token: Address) -> Result<i128, LockReleaseError> {
+        // Only the owner can sweep revenue
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let revenue_data: RevenueData = env
+            .storage()
+            .instance()
+            .get(&DataKey::Revenue)
+            .ok_or(LockReleaseError::RevenueNotSet)?;
+        let revenue_key = DataKey::AccumulatedRevenue(token.clone());
+        let accumulated_revenue: i128 = env.storage().instance().get(&revenue_key).unwrap_or(0);
+        if accumulated_revenue > 0 {
+            token::Client::new(&env, &token).transfer(
+                &env.current_contract_address(),
+                &revenue_data.revenue_address,
+                &accumulated_revenue,
+            );
+            env.storage().instance().set(&revenue_key, &0_i128);
+        }
+
+        // Emit event for transparency
+        env.events()
+            .publish(("RevenueSwept", token), accumulated_revenue);
+        Ok(accumulated_revenue)
+    }
+
+    pub fn get_accumulated_revenue(env: Env, token: Address) -> i128 {
+        env.storage()
+            .instance()
+            .get(&DataKey::AccumulatedRevenue(token))
+            .unwrap_or(0)
+    }
+
+    pub fn set_custody_mode(
+        env: Env,
+        token: Address,
+        mode: CustodyMode,
+    ) -> Result<(), LockReleaseError> {
+        // Only the owner can change the custody mode
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        // Leaving escrow would strand the escrowed funds, as releases would
+        // be paid by the admin instead
+        if mode == CustodyMode::ForwardToAdmin && get_escrow_balance(&env, &token) > 0 {
+            return Err(LockReleaseError::EscrowNotEmpty);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::CustodyMode(token.clone()), &mode);
+
+        // Emit event for transparency
+        env.events().publish(("CustodyModeUpdated", token), mode);
+        Ok(())
+    }
+
+    pub fn get_custody_mode(env: Env, token: Address) -> CustodyMode {
+        get_custody_mode(&env, &token)
+    }
+
+    /// Returns the amount of `token` held in escrow by the contract for
+    /// pending releases, excluding unswept revenue.
+    pub fn get_escrow_balance(env: Env, token: Address) -> i128 {
+        get_escrow_balance(&env, &token)
+    }
+
+    /// Registers `token` as a lockable source token, or updates its
+    /// metadata if it is already registered.
+    pub fn set_supported_token(
+        env: Env,
+        token: Address,
+        decimals: u32,
+        symbol: String,
+        enabled: bool,
+    ) -> Result<(), LockReleaseError> {
+        // Only the owner can manage the token registry
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let mut tokens = get_supported_tokens(&env);
+        if !tokens.contains(&token) {
+            tokens.push_back(token.clone());
+            env.storage()
+                .instance()
+                .set(&DataKey::SupportedTokens, &tokens);
+        }
+        let info = TokenInfo {
+            decimals,
+            symbol,
+            enabled,
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::TokenInfo(token.clone()), &info);
+
+        // Emit event for transparency
+        env.events().publish(("TokenInfoUpdated", token), info);
+        Ok(())
+    }
+
+    pub fn remove_supported_token(env: Env, token: Address) -> Result<(), LockReleaseError> {
+        // Only the owner can manage the token registry
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let mut tokens = get_supported_tokens(&env);
+        let index = tokens
+            .first_index_of(&token)
+            .ok_or(LockReleaseError::TokenNotSupported)?;
+        tokens.remove(index);
+        env.storage()
+            .instance()
+            .set(&DataKey::SupportedTokens, &tokens);
+        env.storage()
+            .instance()
+            .remove(&DataKey::TokenInfo(token.clone()));
+
+        // Emit event for transparency
+        env.events().publish(("TokenRemoved", token), 1);
+        Ok(())
+    }
+
+    pub fn get_token_info(env: Env, token: Address) -> Option<TokenInfo> {
+        env.storage().instance().get(&DataKey::TokenInfo(token))
+    }
+
+    /// Returns the registered tokens that `lock` currently accepts.
+    pub fn get_supported_tokens(env: Env) -> Vec<Address> {
+        let mut enabled = Vec::new(&env);
+        for token in get_supported_tokens(&env).iter() {
+            if require_token_supported(&env, &token).is_ok() {
+                enabled.push_back(token);
+            }
+        }
+        enabled
+    }
+
+    /// Registers `chain_id` as a destination chain for `lock`, or updates
+    /// its metadata if it is already registered.
+    pub fn set_supported_chain(
+        env: Env,
+        chain_id: u64,
+        name: String,
+        address_format: AddressFormat,
+        enabled: bool,
+    ) -> Result<(), LockReleaseError> {
+        // Only the owner can manage the chain registry
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let mut chains = get_supported_chains(&env);
+        if !chains.contains(chain_id) {
+            chains.push_back(chain_id);
+            env.storage()
+                .instance()
+                .set(&DataKey::SupportedChains, &chains);
+        }
+        let info = ChainInfo {
+            name,
+            address_format,
+            enabled,
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::ChainInfo(chain_id), &info);
+
+        // Emit event for transparency
+        env.events().publish(("ChainInfoUpdated", chain_id), info);
+        Ok(())
+    }
+
+    pub fn remove_supported_chain(env: Env, chain_id: u64) -> Result<(), LockReleaseError> {
+        // Only the owner can manage the chain registry
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let mut chains = get_supported_chains(&env);
+        let index = chains
+            .first_index_of(chain_id)
+            .ok_or(LockReleaseError::ChainNotSupported)?;
+        chains.remove(index);
+        env.storage()
+            .instance()
+            .set(&DataKey::SupportedChains, &chains);
+        env.storage()
+            .instance()
+            .remove(&DataKey::ChainInfo(chain_id));
+
+        // Emit event for transparency
+        env.events().publish(("ChainRemoved", chain_id), 1);
+        Ok(())
+    }
+
+    pub fn get_chain_info(env: Env, chain_id: u64) -> Option<ChainInfo> {
+        env.storage().instance().get(&DataKey::ChainInfo(chain_id))
+    }
+
+    /// Returns the IDs of the registered chains that `lock` currently
+    /// accepts.
+    pub fn get_active_chains(env: Env) -> Vec<u64> {
+        let mut active = Vec::new(&env);
+        for chain_id in get_supported_chains(&env).iter() {
+            if get_active_chain(&env, chain_id).is_ok() {
+                active.push_back(chain_id);
+            }
+        }
+        active
+    }
+
+    /// Allows locks of `from_token` to `dest_chain` to request `dest_token`.
+    pub fn add_route(
+        env: Env,
+        from_token: Address,
+        dest_chain: u64,
+        dest_token: String,
+    ) -> Result<(), LockReleaseError> {
+        // Only the owner can manage routes
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let mut dest_tokens = get_route(&env, &from_token, dest_chain);
+        if !dest_tokens.contains(&dest_token) {
+            dest_tokens.push_back(dest_token.clone());
+            env.storage().instance().set(
+                &DataKey::Route(from_token.clone(), dest_chain),
+                &dest_tokens,
+            );
+        }
+        let mut chains = get_route_chains(&env, &from_token);
+        if !chains.contains(dest_chain) {
+            chains.push_back(dest_chain);
+            env.storage()
+                .instance()
+                .set(&DataKey::RouteChains(from_token.clone()), &chains);
+        }
+
+        // Emit event for transparency
+        env.events()
+            .publish(("RouteAdded", from_token, dest_chain), dest_token);
+        Ok(())
+    }
+
+    pub fn remove_route(
+        env: Env,
+        from_token: Address,
+        dest_chain: u64,
+        dest_token: String,
+    ) -> Result<(), LockReleaseError> {
+        // Only the owner can manage routes
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let mut dest_tokens = get_route(&env, &from_token, dest_chain);
+        let index = dest_tokens
+            .first_index_of(&dest_token)
+            .ok_or(LockReleaseError::RouteNotSupported)?;
+        dest_tokens.remove(index);
+
+        let route_key = DataKey::Route(from_token.clone(), dest_chain);
+        if dest_tokens.is_empty() {
+            env.storage().instance().remove(&route_key);
+            let mut chains = get_route_chains(&env, &from_token);
+            if let Some(index) = chains.first_index_of(dest_chain) {
+                chains.remove(index);
+            }
+            env.storage()
+                .instance()
+                .set(&DataKey::RouteChains(from_token.clone()), &chains);
+        } else {
+            env.storage().instance().set(&route_key, &dest_tokens);
+        }
+
+        // Emit event for transparency
+        env.events()
+            .publish(("RouteRemoved", from_token, dest_chain), dest_token);
+        Ok(())
+    }
 
-#[contractimpl]
-impl LockAndReleaseContract {
-    pub fn initialize(env: Env, owner: Address) {
-        // Prevent re-initialization
-        if env.storage().instance().has(&DataKey::Init) {
-            env.panic_with_error(Error::from_type_and_code(
-                ScErrorType::Contract,
-                ScErrorCode::ExistingValue,
-            ));
+    /// Returns the destination tokens `from_token` can currently be bridged
+    /// to, grouped by active destination chain.
+    pub fn get_routes(env: Env, from_token: Address) -> Vec<Route> {
+        let mut routes = Vec::new(&env);
+        for dest_chain in get_route_chains(&env, &from_token).iter() {
+            if get_active_chain(&env, dest_chain).is_ok() {
+                routes.push_back(Route {
+                    dest_chain,
+                    dest_tokens: get_route(&env, &from_token, dest_chain),
+                });
+            }
         }
+        routes
+    }
 
-        // Authenticate the caller as the owner
+    /// Limits the `kind` volume of `token` to `max_amount` per `window`
+    /// seconds.
+    pub fn set_rate_limit(
+        env: Env,
+        token: Address,
+        kind: RateLimitKind,
+        max_amount: i128,
+        window: u64,
+    ) -> Result<(), LockReleaseError> {
+        // Only the owner can change rate limits
+        let owner = get_owner(&env)?;
         owner.require_auth();
 
-        // Set the contract owner and mark as initialized
-        env.storage().instance().set(&DataKey::Owner, &owner);
-        env.storage().instance().set(&DataKey::Init, &());
+        if max_amount < 1 || window == 0 {
+            return Err(LockReleaseError::InvalidThreshold);
+        }
+        let limit = RateLimit { max_amount, window };
+        env.storage()
+            .instance()
+            .set(&DataKey::RateLimit(token.clone(), kind), &limit);
+
+        // Emit event for transparency
+        env.events()
+            .publish(("RateLimitUpdated", token, kind), limit);
+        Ok(())
+    }
+
+    pub fn clear_rate_limit(
+        env: Env,
+        token: Address,
+        kind: RateLimitKind,
+    ) -> Result<(), LockReleaseError> {
+        // Only the owner can change rate limits
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        env.storage()
+            .instance()
+            .remove(&DataKey::RateLimit(token.clone(), kind));
+
+        // Emit event for transparency
+        env.events().publish(("RateLimitCleared", token, kind), 1);
+        Ok(())
+    }
+
+    pub fn get_rate_limit(env: Env, token: Address, kind: RateLimitKind) -> Option<RateLimit> {
+        env.storage().instance().get(&DataKey::RateLimit(token, kind))
     }
 
-    pub fn set_admin(env: Env, admin: Address) {
-        // Ensure this is a one-time action
-        if env.storage().instance().has(&DataKey::AdminSet) {
-            env.panic_with_error(Error::from_type_and_code(
-                ScErrorType::Contract,
-                ScErrorCode::InvalidAction,
-            ));
+    /// Restricts each lock of `token` to an `in_amount` between
+    /// `min_amount` and `max_amount` inclusive.
+    pub fn set_lock_limits(
+        env: Env,
+        token: Address,
+        min_amount: i128,
+        max_amount: i128,
+    ) -> Result<(), LockReleaseError> {
+        // Only the owner can change lock limits
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        if min_amount < 1 || max_amount < min_amount {
+            return Err(LockReleaseError::InvalidThreshold);
         }
+        let limits = LockLimits {
+            min_amount,
+            max_amount,
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::LockLimits(token.clone()), &limits);
 
-        // Only the owner can set the admin
-        let owner: Address = env.storage().instance().get(&DataKey::Owner).unwrap();
+        // Emit event for transparency
+        env.events().publish(("LockLimitsUpdated", token), limits);
+        Ok(())
+    }
+
+    pub fn clear_lock_limits(env: Env, token: Address) -> Result<(), LockReleaseError> {
+        // Only the owner can change lock limits
+        let owner = get_owner(&env)?;
         owner.require_auth();
 
-        // Set admin and mark as set
-        env.storage().instance().set(&DataKey::Admin, &AdminData {
-            admin_address: admin.clone(),
-        });
-        env.storage().instance().set(&DataKey::AdminSet, &());
+        env.storage()
+            .instance()
+            .remove(&DataKey::LockLimits(token.clone()));
 
         // Emit event for transparency
-        let topics = ("AdminSetEvent", admin);
-        env.events().publish(topics, 1);
+        env.events().publish(("LockLimitsCleared", token), 1);
+        Ok(())
     }
 
-    pub fn set_revenue_address(env: Env, revenue_address: Address) {
-        // Ensure this is a one-time action
-        if env.storage().instance().has(&DataKey::RevenueSet) {
-            env.panic_with_error(Error::from_type_and_code(
-                ScErrorType::Contract,
-                ScErrorCode::InvalidAction,
-            ));
+    pub fn get_lock_limits(env: Env, token: Address) -> Option<LockLimits> {
+        env.storage().instance().get(&DataKey::LockLimits(token))
+    }
+
+    /// Tracks releases of `token` with a token bucket of `capacity` that
+    /// refills by `refill_per_second`. A release larger than the bucket
+    /// trips the breaker and halts releases of the token until the owner
+    /// calls `reset_outflow_breaker`.
+    ///
+    /// The trip is a separate per-token flag, reported by
+    /// `is_outflow_tripped`, rather than the pause flags: the pauser cannot
+    /// clear it, and `is_paused`/`is_token_paused` stay false, so locks and
+    /// releases of other tokens continue.
+    pub fn set_outflow_breaker(
+        env: Env,
+        token: Address,
+        capacity: i128,
+        refill_per_second: i128,
+    ) -> Result<(), LockReleaseError> {
+        // Only the owner can configure the breaker
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        if capacity < 1 || refill_per_second < 0 {
+            return Err(LockReleaseError::InvalidThreshold);
         }
+        let breaker = OutflowBreaker {
+            capacity,
+            refill_per_second,
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::OutflowBreaker(token.clone()), &breaker);
+        env.storage()
+            .instance()
+            .remove(&DataKey::OutflowBucket(token.clone()));
 
-        // Only the owner can set the revenue address
-        let owner: Address = env.storage().instance().get(&DataKey::Owner).unwrap();
+        // Emit event for transparency
+        env.events()
+            .publish(("OutflowBreakerUpdated", token), breaker);
+        Ok(())
+    }
+
+    pub fn clear_outflow_breaker(env: Env, token: Address) -> Result<(), LockReleaseError> {
+        // Only the owner can configure the breaker
+        let owner = get_owner(&env)?;
         owner.require_auth();
 
-        // Set revenue address and mark as set
-        env.storage().instance().set(&DataKey::Revenue, &RevenueData {
-            revenue_address: revenue_address.clone(),
-        });
-        env.storage().instance().set(&DataKey::RevenueSet, &());
+        env.storage()
+            .instance()
+            .remove(&DataKey::OutflowBreaker(token.clone()));
+        env.storage()
+            .instance()
+            .remove(&DataKey::OutflowBucket(token.clone()));
 
-        // Initialize accumulated revenue to 0
-        env.storage().instance().set(&DataKey::AccumulatedRevenue, &0_i128);
+        // Emit event for transparency
+        env.events().publish(("OutflowBreakerCleared", token), 1);
+        Ok(())
+    }
+
+    /// Re-enables releases of `token` after the breaker tripped, with a
+    /// full bucket.
+    pub fn reset_outflow_breaker(env: Env, token: Address) -> Result<(), LockReleaseError> {
+        // Only the owner can clear a tripped breaker
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        env.storage()
+            .instance()
+            .remove(&DataKey::OutflowTripped(token.clone()));
+        env.storage()
+            .instance()
+            .remove(&DataKey::OutflowBucket(token.clone()));
 
         // Emit event for transparency
-        let topics = ("RevenueAddressSetEvent", revenue_address);
-        env.events().publish(topics, 1);
+        env.events().publish(("OutflowBreakerReset", token), 1);
+        Ok(())
+    }
+
+    pub fn get_outflow_breaker(env: Env, token: Address) -> Option<OutflowBreaker> {
+        env.storage()
+            .instance()
+            .get(&DataKey::OutflowBreaker(token))
+    }
+
+    pub fn is_outflow_tripped(env: Env, token: Address) -> bool {
+        is_flag_set(&env, &DataKey::OutflowTripped(token))
+    }
+
+    pub fn set_pauser(env: Env, pauser: Address) -> Result<(), LockReleaseError> {
+        // Only the owner can appoint the pauser
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        env.storage().instance().set(&DataKey::Pauser, &pauser);
+
+        // Emit event for transparency
+        env.events().publish(("PauserSetEvent", pauser), 1);
+        Ok(())
     }
 
+    /// Pauses or unpauses `lock` and `release` for every token.
+    pub fn set_paused(env: Env, caller: Address, paused: bool) -> Result<(), LockReleaseError> {
+        require_owner_or_pauser(&env, &caller)?;
+
+        env.storage().instance().set(&DataKey::Paused, &paused);
+
+        env.events().publish(("PauseUpdated", caller), paused);
+        Ok(())
+    }
+
+    pub fn set_token_paused(
+        env: Env,
+        caller: Address,
+        token: Address,
+        paused: bool,
+    ) -> Result<(), LockReleaseError> {
+        require_owner_or_pauser(&env, &caller)?;
+
+        env.storage()
+            .instance()
+            .set(&DataKey::TokenPaused(token.clone()), &paused);
+
+        env.events()
+            .publish(("TokenPauseUpdated", caller, token), paused);
+        Ok(())
+    }
+
+    pub fn set_chain_paused(
+        env: Env,
+        caller: Address,
+        dest_chain: u64,
+        paused: bool,
+    ) -> Result<(), LockReleaseError> {
+        require_owner_or_pauser(&env, &caller)?;
+
+        env.storage()
+            .instance()
+            .set(&DataKey::ChainPaused(dest_chain), &paused);
+
+        env.events()
+            .publish(("ChainPauseUpdated", caller, dest_chain), paused);
+        Ok(())
+    }
+
+    pub fn is_paused(env: Env) -> bool {
+        is_flag_set(&env, &DataKey::Paused)
+    }
+
+    pub fn is_token_paused(env: Env, token: Address) -> bool {
+        is_flag_set(&env, &DataKey::TokenPaused(token))
+    }
+
+    pub fn is_chain_paused(env: Env, dest_chain: u64) -> bool {
+        is_flag_set(&env, &DataKey::ChainPaused(dest_chain))
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn lock(
         env: Env,
         user_address: Address,
@@ -134,84 +1834,138 @@ impl LockAndReleaseContract {
         dest_token: String,
         src_token: Address,
         in_amount: i128,
-        dest_chain: Bytes,
-        recipient_address: String,
-    ) {
+        dest_chain: u64,
+        recipient_address: Recipient,
+    ) -> Result<u64, LockReleaseError> {
         // Authenticate user
         user_address.require_auth();
 
         // Ensure admin is configured
-        if !env.storage().instance().has(&DataKey::Admin) {
-            env.panic_with_error(Error::from_type_and_code(
-                ScErrorType::Contract,
-                ScErrorCode::MissingValue,
-            ));
+        let admin_address = get_admin(&env)?;
+
+        // Only registered, enabled tokens can be locked
+        require_token_supported(&env, &from_token)?;
+
+        // Only registered, enabled destination chains can be locked to,
+        // and the recipient must match the chain's address format
+        let chain = get_active_chain(&env, dest_chain)?;
+        let recipient_address = validate_recipient(&env, chain.address_format, recipient_address)?;
+
+        // The destination token must be routed from this token and chain
+        if !get_route(&env, &from_token, dest_chain).contains(&dest_token) {
+            return Err(LockReleaseError::RouteNotSupported);
+        }
+
+        // Ensure locking is not paused for this token or destination chain
+        require_token_active(&env, &from_token)?;
+        if is_flag_set(&env, &DataKey::ChainPaused(dest_chain)) {
+            return Err(LockReleaseError::ChainPaused);
         }
 
         // Validate amount
         if in_amount < 1 {
-            env.panic_with_error(Error::from_type_and_code(
-                ScErrorType::Contract,
-                ScErrorCode::InvalidAction,
-            ));
+            return Err(LockReleaseError::InvalidAmount);
+        }
+        let limits: Option<LockLimits> = env
+            .storage()
+            .instance()
+            .get(&DataKey::LockLimits(from_token.clone()));
+        if let Some(limits) = limits {
+            if in_amount < limits.min_amount {
+                return Err(LockReleaseError::LockBelowMinimum);
+            }
+            if in_amount > limits.max_amount {
+                return Err(LockReleaseError::LockAboveMaximum);
+            }
         }
 
-        // Calculate swaped amount (3% fee)
-        let swaped_amount = in_amount - (in_amount * 3 / 100);
+        // Calculate protocol fee and swaped amount
+        let revenue_amount =
+            in_amount * get_token_fee_bps(&env, &from_token) as i128 / BPS_DENOMINATOR;
+        let swaped_amount = in_amount - revenue_amount;
         if swaped_amount < 1 {
-            env.panic_with_error(Error::from_type_and_code(
-                ScErrorType::Contract,
-                ScErrorCode::InvalidAction,
-            ));
+            return Err(LockReleaseError::AmountTooSmall);
         }
 
+        // Enforce the user's and the token's lock volume limits
+        consume_rate_limit(
+            &env,
+            &from_token,
+            RateLimitKind::UserLock,
+            DataKey::UserRateUsage(user_address.clone(), from_token.clone()),
+            in_amount,
+        )?;
+        consume_rate_limit(
+            &env,
+            &from_token,
+            RateLimitKind::TokenLock,
+            DataKey::TokenRateUsage(from_token.clone(), RateLimitKind::TokenLock),
+            in_amount,
+        )?;
+
         // Transfer input tokens to the contract
         token::Client::new(&env, &from_token)
             .transfer(&user_address, &env.current_contract_address(), &in_amount);
 
-        // Fetch admin and transfer swaped amount to them
-        let admin_data: AdminData = env.storage().instance().get(&DataKey::Admin).unwrap();
-        let admin_address = admin_data.admin_address;
-
-        token::Client::new(&env, &from_token)
-            .transfer(&env.current_contract_address(), &admin_address, &swaped_amount);
+        // Keep swaped amount in escrow, or forward it to the admin
+        let custody = get_custody_mode(&env, &from_token);
+        match custody {
+            CustodyMode::Escrow => {
+                let escrow_balance = get_escrow_balance(&env, &from_token);
+                set_escrow_balance(&env, &from_token, escrow_balance + swaped_amount);
+            }
+            CustodyMode::ForwardToAdmin => {
+                token::Client::new(&env, &from_token)
+                    .transfer(&env.current_contract_address(), &admin_address, &swaped_amount);
+            }
+        }
 
-        // Calculate and accumulate revenue (3% of in_amount)
-        let revenue_amount = in_amount * 3 / 100;
-        let mut accumulated_revenue: i128 = env.storage().instance().get(&DataKey::AccumulatedRevenue).unwrap_or(0);
+        // Accumulate revenue for this token
+        let revenue_key = DataKey::AccumulatedRevenue(from_token.clone());
+        let mut accumulated_revenue: i128 = env.storage().instance().get(&revenue_key).unwrap_or(0);
         accumulated_revenue += revenue_amount;
-        env.storage().instance().set(&DataKey::AccumulatedRevenue, &accumulated_revenue);
+        env.storage().instance().set(&revenue_key, &accumulated_revenue);
 
-        // Check if accumulated revenue has reached 100 USDC (100 * 10^6 since USDC has 6 decimals)
-        if accumulated_revenue >= 100_000_000 {
+        // Sweep once the token's accumulated revenue reaches its configured threshold
+        let sweep_threshold: Option<i128> = env
+            .storage()
+            .instance()
+            .get(&DataKey::SweepThreshold(from_token.clone()));
+        if sweep_threshold.is_some_and(|threshold| accumulated_revenue >= threshold) {
             // Transfer accumulated revenue to revenue address
             if env.storage().instance().has(&DataKey::Revenue) {
                 let revenue_data: RevenueData = env.storage().instance().get(&DataKey::Revenue).unwrap();
                 token::Client::new(&env, &from_token)
                     .transfer(&env.current_contract_address(), &revenue_data.revenue_address, &accumulated_revenue);
-                
+
                 // Reset accumulated revenue
-                env.storage().instance().set(&DataKey::AccumulatedRevenue, &0_i128);
+                env.storage().instance().set(&revenue_key, &0_i128);
             }
         }
 
-        // Emit lock event with src_token
-        let topics = (
-            "LockEvent",
-            user_address.clone(),
-            dest_token.clone(),
-            src_token.clone(),
-            in_amount,
-            swaped_amount,
-            recipient_address.clone(),
-            dest_chain.clone(),
-            from_token.clone(),
+        // Assign a unique ID to this lock
+        let lock_id: u64 = env.storage().instance().get(&DataKey::LockNonce).unwrap_or(0);
+        env.storage().instance().set(&DataKey::LockNonce, &(lock_id + 1));
+
+        // Emit lock event, indexed by user and source token
+        env.events().publish(
+            (symbol_short!("Lock"), user_address.clone(), from_token.clone()),
+            LockEvent {
+                lock_id,
+                user_address: user_address.clone(),
+                from_token: from_token.clone(),
+                src_token: src_token.clone(),
+                dest_token: dest_token.clone(),
+                in_amount,
+                swaped_amount,
+                recipient_address: recipient_address.clone(),
+                dest_chain,
+            },
         );
-        env.events().publish(topics, 1);
 
-        // Store lock data specific to user (prevents overwriting and DoS risk)
-        env.storage().instance().set(
-            &DataKey::LockData(user_address.clone()),
+        // Store lock data under its own ID so repeated locks never overwrite each other
+        env.storage().persistent().set(
+            &DataKey::Lock(lock_id),
             &LockData {
                 user_address,
                 dest_token,
@@ -221,26 +1975,530 @@ impl LockAndReleaseContract {
                 swaped_amount,
                 recipient_address,
                 dest_chain,
+                timestamp: env.ledger().timestamp(),
+                custody,
+                status: LockStatus::Pending,
             },
         );
+        Ok(lock_id)
+    }
+
+    pub fn get_lock(env: Env, lock_id: u64) -> Result<LockData, LockReleaseError> {
+        get_lock(&env, lock_id)
+    }
+
+    pub fn set_refund_expiry(env: Env, expiry: u64) -> Result<(), LockReleaseError> {
+        // Only the owner can change the refund expiry
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        env.storage().instance().set(&DataKey::RefundExpiry, &expiry);
+
+        // Emit event for transparency
+        env.events().publish(("RefundExpiryUpdated",), expiry);
+        Ok(())
+    }
+
+    pub fn get_refund_expiry(env: Env) -> u64 {
+        get_refund_expiry(&env)
+    }
+
+    /// Marks a lock as completed on the destination chain, so it can no
+    /// longer be refunded.
+    pub fn mark_fulfilled(env: Env, lock_id: u64) -> Result<(), LockReleaseError> {
+        // Only the admin can mark locks as fulfilled
+        let admin = get_admin(&env)?;
+        admin.require_auth();
+
+        let mut lock_data = get_lock(&env, lock_id)?;
+        if lock_data.status != LockStatus::Pending {
+            return Err(LockReleaseError::LockNotPending);
+        }
+        lock_data.status = LockStatus::Fulfilled;
+        env.storage().persistent().set(&DataKey::Lock(lock_id), &lock_data);
+
+        env.events().publish(("LockFulfilled", lock_id), 1);
+        Ok(())
+    }
+
+    /// Returns the swaped amount of an unfulfilled lock to its user once the
+    /// refund expiry has passed. Funds come from escrow, or are pulled from
+    /// the admin using the allowance it granted to this contract.
+    pub fn refund(env: Env, lock_id: u64) -> Result<(), LockReleaseError> {
+        let mut lock_data = get_lock(&env, lock_id)?;
+        lock_data.user_address.require_auth();
+
+        if lock_data.status != LockStatus::Pending {
+            return Err(LockReleaseError::LockNotPending);
+        }
+        if env.ledger().timestamp() < lock_data.timestamp.saturating_add(get_refund_expiry(&env)) {
+            return Err(LockReleaseError::RefundNotAvailable);
+        }
+
+        let token_client = token::Client::new(&env, &lock_data.from_token);
+        let contract = env.current_contract_address();
+        let amount = lock_data.swaped_amount;
+        match lock_data.custody {
+            CustodyMode::Escrow => {
+                let escrow_balance = get_escrow_balance(&env, &lock_data.from_token);
+                if escrow_balance < amount {
+                    return Err(LockReleaseError::InsufficientEscrowBalance);
+                }
+                set_escrow_balance(&env, &lock_data.from_token, escrow_balance - amount);
+                token_client.transfer(&contract, &lock_data.user_address, &amount);
+            }
+            CustodyMode::ForwardToAdmin => {
+                let admin = get_admin(&env)?;
+                if token_client.allowance(&admin, &contract) < amount {
+                    return Err(LockReleaseError::InsufficientAdminAllowance);
+                }
+                token_client.transfer_from(&contract, &admin, &lock_data.user_address, &amount);
+            }
+        }
+
+        lock_data.status = LockStatus::Refunded;
+        env.storage().persistent().set(&DataKey::Lock(lock_id), &lock_data);
+
+        env.events().publish(
+            (symbol_short!("Refund"), lock_data.user_address),
+            (lock_id, lock_data.from_token, amount),
+        );
+        Ok(())
     }
 
-    pub fn release(env: Env, amount: i128, user: Address, destination_token: Address) {
+    /// Releases funds to `user`. Returns whether the funds were paid, or
+    /// false if the outflow breaker tripped and held the release.
+    pub fn release(
+        env: Env,
+        amount: i128,
+        user: Address,
+        destination_token: Address,
+        source_transfer: SourceTransfer,
+    ) -> Result<bool, LockReleaseError> {
         // Retrieve admin and authenticate
-        let admin_data: AdminData = env.storage().instance().get(&DataKey::Admin).unwrap();
-        let admin = admin_data.admin_address;
+        let admin = get_admin(&env)?;
+        admin.require_auth();
+
+        // Once a relayer set is active, releases need relayer approvals
+        if get_relayer_threshold(&env) > 0 {
+            return Err(LockReleaseError::RelayerApprovalRequired);
+        }
+
+        execute_release(&env, user, destination_token, amount, source_transfer, true)
+    }
+
+    /// Replaces the validator set and starts a new epoch. Signatures made
+    /// for earlier epochs are no longer accepted.
+    pub fn set_validator_set(
+        env: Env,
+        public_keys: Vec<BytesN<32>>,
+        threshold: u32,
+    ) -> Result<u32, LockReleaseError> {
+        // Only the owner can manage validators
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        if threshold == 0 || threshold > public_keys.len() {
+            return Err(LockReleaseError::InvalidThreshold);
+        }
+        if has_duplicates(&public_keys) {
+            return Err(LockReleaseError::DuplicateValidator);
+        }
+
+        let epoch = env
+            .storage()
+            .instance()
+            .get::<_, ValidatorSet>(&DataKey::ValidatorSet)
+            .map_or(0, |set| set.epoch + 1);
+        env.storage().instance().set(&DataKey::ValidatorSet, &ValidatorSet {
+            epoch,
+            public_keys,
+            threshold,
+        });
+
+        // Emit event for transparency
+        env.events().publish(("ValidatorSetUpdated",), (epoch, threshold));
+        Ok(epoch)
+    }
+
+    pub fn get_validator_set(env: Env) -> Option<ValidatorSet> {
+        env.storage().instance().get(&DataKey::ValidatorSet)
+    }
+
+    /// Releases funds on the strength of a quorum of validator signatures
+    /// over `payload`, so any party can submit the transaction. Invalid
+    /// signatures abort the call. Returns whether the funds were paid, as
+    /// `release` does.
+    pub fn release_with_signatures(
+        env: Env,
+        payload: ReleasePayload,
+        signatures: Vec<ValidatorSignature>,
+    ) -> Result<bool, LockReleaseError> {
+        let validator_set: ValidatorSet = env
+            .storage()
+            .instance()
+            .get(&DataKey::ValidatorSet)
+            .ok_or(LockReleaseError::ValidatorSetNotConfigured)?;
+        check_release_payload(&env, &payload, validator_set.epoch)?;
+
+        let message = payload.clone().to_xdr(&env);
+        let mut signers: Vec<BytesN<32>> = Vec::new(&env);
+        for signature in signatures.iter() {
+            if !validator_set.public_keys.contains(&signature.public_key) {
+                return Err(LockReleaseError::UnknownValidator);
+            }
+            if signers.contains(&signature.public_key) {
+                return Err(LockReleaseError::DuplicateSignature);
+            }
+            env.crypto()
+                .ed25519_verify(&signature.public_key, &message, &signature.signature);
+            signers.push_back(signature.public_key);
+        }
+        if signers.len() < validator_set.threshold {
+            return Err(LockReleaseError::InsufficientSignatures);
+        }
+
+        execute_release(
+            &env,
+            payload.user,
+            payload.token,
+            payload.amount,
+            payload.source_transfer,
+            false,
+        )
+    }
+
+    /// Replaces the EVM validator set and starts a new epoch for
+    /// `release_with_evm_signatures`.
+    pub fn set_evm_validator_set(
+        env: Env,
+        signers: Vec<BytesN<20>>,
+        threshold: u32,
+    ) -> Result<u32, LockReleaseError> {
+        // Only the owner can manage validators
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        if threshold == 0 || threshold > signers.len() {
+            return Err(LockReleaseError::InvalidThreshold);
+        }
+        if has_duplicates(&signers) {
+            return Err(LockReleaseError::DuplicateValidator);
+        }
+
+        let epoch = env
+            .storage()
+            .instance()
+            .get::<_, EvmValidatorSet>(&DataKey::EvmValidatorSet)
+            .map_or(0, |set| set.epoch + 1);
+        env.storage().instance().set(&DataKey::EvmValidatorSet, &EvmValidatorSet {
+            epoch,
+            signers,
+            threshold,
+        });
+
+        // Emit event for transparency
+        env.events()
+            .publish(("EvmValidatorSetUpdated",), (epoch, threshold));
+        Ok(epoch)
+    }
+
+    pub fn get_evm_validator_set(env: Env) -> Option<EvmValidatorSet> {
+        env.storage().instance().get(&DataKey::EvmValidatorSet)
+    }
+
+    /// Releases funds on the strength of a quorum of secp256k1 signatures
+    /// from registered EVM validator addresses. Returns whether the funds
+    /// were paid, as `release` does.
+    pub fn release_with_evm_signatures(
+        env: Env,
+        payload: ReleasePayload,
+        signatures: Vec<EvmSignature>,
+    ) -> Result<bool, LockReleaseError> {
+        let validator_set: EvmValidatorSet = env
+            .storage()
+            .instance()
+            .get(&DataKey::EvmValidatorSet)
+            .ok_or(LockReleaseError::ValidatorSetNotConfigured)?;
+        check_release_payload(&env, &payload, validator_set.epoch)?;
+
+        let mut signers: Vec<BytesN<20>> = Vec::new(&env);
+        for signature in signatures.iter() {
+            let signer = recover_evm_signer(&env, &payload, &signature)?;
+            if !validator_set.signers.contains(&signer) {
+                return Err(LockReleaseError::UnknownValidator);
+            }
+            if signers.contains(&signer) {
+                return Err(LockReleaseError::DuplicateSignature);
+            }
+            signers.push_back(signer);
+        }
+        if signers.len() < validator_set.threshold {
+            return Err(LockReleaseError::InsufficientSignatures);
+        }
+
+        execute_release(
+            &env,
+            payload.user,
+            payload.token,
+            payload.amount,
+            payload.source_transfer,
+            false,
+        )
+    }
+
+    /// Posts the Merkle root of a batch of pending releases. Each root can
+    /// be posted once per batch ID.
+    pub fn post_release_root(
+        env: Env,
+        batch_id: u64,
+        root: BytesN<32>,
+    ) -> Result<(), LockReleaseError> {
+        // Only the admin can post release batches
+        let admin = get_admin(&env)?;
         admin.require_auth();
 
-        // Check admin's balance
-        let admin_balance = token::Client::new(&env, &destination_token).balance(&admin);
-        if admin_balance < amount {
-            env.panic_with_error(Error::from_type_and_code(
-                ScErrorType::Contract,
-                ScErrorCode::InvalidAction,
-            ));
+        // A root is signed by the admin alone, so batches are unavailable
+        // while releases need relayer approvals
+        if get_relayer_threshold(&env) > 0 {
+            return Err(LockReleaseError::RelayerApprovalRequired);
+        }
+
+        let root_key = DataKey::ReleaseRoot(batch_id);
+        if env.storage().persistent().has(&root_key) {
+            return Err(LockReleaseError::BatchAlreadyPosted);
+        }
+        env.storage().persistent().set(&root_key, &root);
+
+        // Emit event for transparency
+        env.events().publish(("ReleaseRootPosted", batch_id), root);
+        Ok(())
+    }
+
+    pub fn get_release_root(env: Env, batch_id: u64) -> Option<BytesN<32>> {
+        env.storage().persistent().get(&DataKey::ReleaseRoot(batch_id))
+    }
+
+    /// Pays out one leaf of a posted batch. Anyone can submit the claim;
+    /// funds always go to `leaf.user`. Returns whether the funds were paid,
+    /// as `release` does; a held leaf stays unclaimed.
+    pub fn claim(
+        env: Env,
+        batch_id: u64,
+        leaf: ReleaseLeaf,
+        proof: Vec<BytesN<32>>,
+    ) -> Result<bool, LockReleaseError> {
+        // Roots posted before relayer mode was enabled cannot bypass it
+        if get_relayer_threshold(&env) > 0 {
+            return Err(LockReleaseError::RelayerApprovalRequired);
+        }
+
+        let root: BytesN<32> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::ReleaseRoot(batch_id))
+            .ok_or(LockReleaseError::BatchNotFound)?;
+
+        let leaf_hash = hash_release_leaf(&env, &leaf);
+        if compute_merkle_root(&env, leaf_hash.clone(), &proof) != root {
+            return Err(LockReleaseError::InvalidProof);
+        }
+
+        let claimed_key = DataKey::Claimed(batch_id, leaf_hash);
+        if env.storage().persistent().has(&claimed_key) {
+            return Err(LockReleaseError::AlreadyProcessed);
+        }
+
+        let released = execute_release(
+            &env,
+            leaf.user,
+            leaf.token,
+            leaf.amount,
+            leaf.source_transfer,
+            false,
+        )?;
+        if released {
+            env.storage().persistent().set(&claimed_key, &());
+        }
+        Ok(released)
+    }
+
+    pub fn is_claimed(env: Env, batch_id: u64, leaf: ReleaseLeaf) -> bool {
+        let leaf_hash = hash_release_leaf(&env, &leaf);
+        env.storage()
+            .persistent()
+            .has(&DataKey::Claimed(batch_id, leaf_hash))
+    }
+
+    pub fn add_relayer(env: Env, relayer: Address) -> Result<(), LockReleaseError> {
+        // Only the owner can manage relayers
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let mut relayers = get_relayers(&env);
+        if !relayers.contains(&relayer) {
+            relayers.push_back(relayer.clone());
+            env.storage().instance().set(&DataKey::Relayers, &relayers);
+        }
+
+        // Emit event for transparency
+        env.events().publish(("RelayerAdded", relayer), 1);
+        Ok(())
+    }
+
+    pub fn remove_relayer(env: Env, relayer: Address) -> Result<(), LockReleaseError> {
+        // Only the owner can manage relayers
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        let mut relayers = get_relayers(&env);
+        let index = relayers
+            .first_index_of(&relayer)
+            .ok_or(LockReleaseError::NotRelayer)?;
+        relayers.remove(index);
+
+        // Never leave the threshold unreachable
+        if get_relayer_threshold(&env) > relayers.len() {
+            return Err(LockReleaseError::InvalidThreshold);
+        }
+        env.storage().instance().set(&DataKey::Relayers, &relayers);
+
+        // Emit event for transparency
+        env.events().publish(("RelayerRemoved", relayer), 1);
+        Ok(())
+    }
+
+    /// Sets how many relayer approvals a release needs. A threshold of 0
+    /// disables relayer mode and lets the admin call `release` directly.
+    pub fn set_relayer_threshold(env: Env, threshold: u32) -> Result<(), LockReleaseError> {
+        // Only the owner can manage relayers
+        let owner = get_owner(&env)?;
+        owner.require_auth();
+
+        if threshold > get_relayers(&env).len() {
+            return Err(LockReleaseError::InvalidThreshold);
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::RelayerThreshold, &threshold);
+
+        // Emit event for transparency
+        env.events().publish(("RelayerThresholdUpdated",), threshold);
+        Ok(())
+    }
+
+    pub fn get_relayers(env: Env) -> Vec<Address> {
+        get_relayers(&env)
+    }
+
+    pub fn get_relayer_threshold(env: Env) -> u32 {
+        get_relayer_threshold(&env)
+    }
+
+    pub fn get_release_request(
+        env: Env,
+        amount: i128,
+        user: Address,
+        destination_token: Address,
+        source_transfer: SourceTransfer,
+    ) -> Option<ReleaseRequest> {
+        env.storage().persistent().get(&DataKey::ReleaseApprovals(
+            source_transfer,
+            user,
+            destination_token,
+            amount,
+        ))
+    }
+
+    /// Records a relayer's approval of a release. Every relayer must approve
+    /// the same user, token and amount; funds move once the number of
+    /// approvals from current relayers reaches the threshold. Approvals of a
+    /// different user, token or amount for the same source transfer are
+    /// counted separately, so one relayer cannot block the others. Returns
+    /// whether the release was executed; if the outflow breaker held it, the
+    /// approvals are kept and any approver can retry after the reset.
+    pub fn approve_release(
+        env: Env,
+        relayer: Address,
+        amount: i128,
+        user: Address,
+        destination_token: Address,
+        source_transfer: SourceTransfer,
+    ) -> Result<bool, LockReleaseError> {
+        relayer.require_auth();
+
+        let relayers = get_relayers(&env);
+        let threshold = get_relayer_threshold(&env);
+        if threshold == 0 || !relayers.contains(&relayer) {
+            return Err(LockReleaseError::NotRelayer);
+        }
+        if env
+            .storage()
+            .persistent()
+            .has(&DataKey::Processed(source_transfer.clone()))
+        {
+            return Err(LockReleaseError::AlreadyProcessed);
+        }
+
+        let approvals_key = DataKey::ReleaseApprovals(
+            source_transfer.clone(),
+            user.clone(),
+            destination_token.clone(),
+            amount,
+        );
+        let mut request: ReleaseRequest = env
+            .storage()
+            .persistent()
+            .get(&approvals_key)
+            .unwrap_or(ReleaseRequest {
+                user: user.clone(),
+                token: destination_token.clone(),
+                amount,
+                approvals: Vec::new(&env),
+            });
+        let already_approved = request.approvals.contains(&relayer);
+        if !already_approved {
+            request.approvals.push_back(relayer.clone());
+        }
+
+        // Only approvals from relayers still in the set count
+        let approval_count = request
+            .approvals
+            .iter()
+            .filter(|approver| relayers.contains(approver))
+            .count() as u32;
+
+        // Approving twice only retries a release that already reached the
+        // threshold but was held by the outflow breaker
+        if already_approved && approval_count < threshold {
+            return Err(LockReleaseError::AlreadyApproved);
         }
 
-        // Perform token release to the user
-        token::Client::new(&env, &destination_token).transfer(&admin, &user, &amount);
+        env.events().publish(
+            ("ReleaseApproved", relayer),
+            (source_transfer.clone(), approval_count),
+        );
+
+        if approval_count < threshold {
+            env.storage().persistent().set(&approvals_key, &request);
+            return Ok(false);
+        }
+
+        let released =
+            execute_release(&env, user, destination_token, amount, source_transfer, false)?;
+        if released {
+            env.storage().persistent().remove(&approvals_key);
+        } else {
+            env.storage().persistent().set(&approvals_key, &request);
+        }
+        Ok(released)
+    }
+
+    pub fn is_processed(env: Env, source_transfer: SourceTransfer) -> bool {
+        env.storage()
+            .persistent()
+            .has(&DataKey::Processed(source_transfer))
     }
 }
+
+mod test;
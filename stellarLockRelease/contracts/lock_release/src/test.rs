@@ -1,17 +1,1627 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{symbol_short, vec, Env};
+use ed25519_dalek::{Signer, SigningKey};
+use k256::ecdsa::SigningKey as EvmSigningKey;
+extern crate std;
+
+use soroban_sdk::{
+    testutils::{
+        Address as _, AuthorizedFunction, AuthorizedInvocation, Events, Ledger, MockAuth,
+        MockAuthInvoke,
+    },
+    token::StellarAssetClient,
+    vec, Env, IntoVal, Symbol, Val,
+};
+
+const ETHEREUM: u64 = 1;
+
+struct Setup<'a> {
+    env: Env,
+    client: LockAndReleaseContractClient<'a>,
+    owner: Address,
+    admin: Address,
+    user: Address,
+    token: Address,
+}
+
+fn create_token(env: &Env) -> Address {
+    let issuer = Address::generate(env);
+    env.register_stellar_asset_contract_v2(issuer).address()
+}
+
+fn setup<'a>() -> Setup<'a> {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register_contract(None, LockAndReleaseContract);
+    let client = LockAndReleaseContractClient::new(&env, &contract_id);
+
+    let owner = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    let token = create_token(&env);
+    StellarAssetClient::new(&env, &token).mint(&user, &1_000_000_000);
+
+    client.initialize(&owner);
+    client.set_admin(&admin);
+    client.set_supported_token(&token, &7, &String::from_str(&env, "USDC"), &true);
+    client.set_supported_chain(
+        &ETHEREUM,
+        &String::from_str(&env, "Ethereum"),
+        &AddressFormat::Evm,
+        &true,
+    );
+    client.add_route(&token, &ETHEREUM, &String::from_str(&env, "USDC"));
+
+    Setup {
+        env,
+        client,
+        owner,
+        admin,
+        user,
+        token,
+    }
+}
+
+fn source_transfer(env: &Env, n: u8) -> SourceTransfer {
+    SourceTransfer {
+        chain_id: 1,
+        transfer_hash: BytesN::from_array(env, &[n; 32]),
+    }
+}
+
+fn recipient(env: &Env) -> Recipient {
+    let mut address = [0; 20];
+    address[19] = 1;
+    Recipient::Evm(BytesN::from_array(env, &address))
+}
+
+fn lock(s: &Setup, amount: i128) -> Result<u64, LockReleaseError> {
+    lock_to(s, amount, ETHEREUM, recipient(&s.env))
+}
+
+fn lock_to(
+    s: &Setup,
+    amount: i128,
+    dest_chain: u64,
+    recipient_address: Recipient,
+) -> Result<u64, LockReleaseError> {
+    let res = s.client.try_lock(
+        &s.user,
+        &s.token,
+        &String::from_str(&s.env, "USDC"),
+        &s.token,
+        &amount,
+        &dest_chain,
+        &recipient_address,
+    );
+    match res {
+        Ok(Ok(lock_id)) => Ok(lock_id),
+        Err(Ok(err)) => Err(err),
+        _ => panic!("unexpected lock result"),
+    }
+}
+
+#[test]
+fn test_initialize_twice() {
+    let s = setup();
+    assert_eq!(
+        s.client.try_initialize(&s.owner),
+        Err(Ok(LockReleaseError::AlreadyInitialized))
+    );
+}
+
+#[test]
+fn test_set_admin_before_initialize() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, LockAndReleaseContract);
+    let client = LockAndReleaseContractClient::new(&env, &contract_id);
+
+    assert_eq!(
+        client.try_set_admin(&Address::generate(&env)),
+        Err(Ok(LockReleaseError::NotInitialized))
+    );
+    assert_eq!(
+        client.try_set_revenue_address(&Address::generate(&env)),
+        Err(Ok(LockReleaseError::NotInitialized))
+    );
+}
+
+#[test]
+fn test_rotate_admin_and_revenue() {
+    let s = setup();
+    let new_admin = Address::generate(&s.env);
+    s.client.set_admin(&new_admin);
+    assert_eq!(s.client.get_admin(), new_admin);
+    assert_eq!(s.client.get_pending_admin(), None);
+
+    let (_, topics, data) = s.env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        ("AdminSetEvent", new_admin.clone()).into_val(&s.env)
+    );
+    let (old_admin, effective_at): (Option<Address>, u64) = data.into_val(&s.env);
+    assert_eq!(old_admin, Some(s.admin.clone()));
+    assert_eq!(effective_at, s.env.ledger().timestamp());
+
+    let revenue = Address::generate(&s.env);
+    s.client.set_revenue_address(&revenue);
+    s.client.set_revenue_address(&Address::generate(&s.env));
+    let (_, _, data) = s.env.events().all().last().unwrap();
+    let old_revenue: Option<Address> = data.into_val(&s.env);
+    assert_eq!(old_revenue, Some(revenue));
+}
+
+#[test]
+fn test_admin_rotation_delay() {
+    let s = setup();
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.admin, &1_000);
+    s.client.set_admin_delay(&3_600);
+
+    let new_admin = Address::generate(&s.env);
+    s.client.set_admin(&new_admin);
+    let pending = s.client.get_pending_admin().unwrap();
+    assert_eq!(pending.admin_address, new_admin);
+
+    // The old admin keeps releasing until the delay passes
+    assert_eq!(s.client.get_admin(), s.admin);
+    s.client
+        .release(&100, &s.user, &s.token, &source_transfer(&s.env, 1));
+
+    s.env.ledger().with_mut(|li| li.timestamp += 3_600);
+    assert_eq!(s.client.get_admin(), new_admin);
+    assert_eq!(s.client.get_pending_admin(), None);
+}
+
+#[test]
+fn test_lowering_admin_delay_waits() {
+    let s = setup();
+    s.client.set_admin_delay(&3_600);
+    assert_eq!(s.client.get_admin_delay(), 3_600);
+
+    // Dropping the delay cannot be used to rotate the admin at once
+    s.client.set_admin_delay(&0);
+    assert_eq!(s.client.get_admin_delay(), 3_600);
+    assert_eq!(
+        s.client.get_pending_admin_delay(),
+        Some(PendingAdminDelay {
+            delay: 0,
+            effective_at: 3_600,
+        })
+    );
+    let new_admin = Address::generate(&s.env);
+    s.client.set_admin(&new_admin);
+    assert_eq!(s.client.get_admin(), s.admin);
+
+    s.env.ledger().with_mut(|li| li.timestamp += 3_600);
+    assert_eq!(s.client.get_admin_delay(), 0);
+    assert_eq!(s.client.get_pending_admin_delay(), None);
+    assert_eq!(s.client.get_admin(), new_admin);
+
+    // Raising the delay applies immediately
+    s.client.set_admin_delay(&7_200);
+    assert_eq!(s.client.get_admin_delay(), 7_200);
+}
+
+#[test]
+fn test_first_admin_applies_immediately() {
+    let env = Env::default();
+    env.mock_all_auths();
+    env.ledger().with_mut(|li| li.timestamp = 1_000);
+    let contract_id = env.register_contract(None, LockAndReleaseContract);
+    let client = LockAndReleaseContractClient::new(&env, &contract_id);
+    client.initialize(&Address::generate(&env));
+    client.set_admin_delay(&3_600);
+
+    // The delay only holds back rotations, and the event says so
+    let admin = Address::generate(&env);
+    client.set_admin(&admin);
+    assert_eq!(client.get_admin(), admin);
+    let (_, _, data) = env.events().all().last().unwrap();
+    let (old_admin, effective_at): (Option<Address>, u64) = data.into_val(&env);
+    assert_eq!((old_admin, effective_at), (None, 1_000));
+
+    client.set_admin(&Address::generate(&env));
+    let (_, _, data) = env.events().all().last().unwrap();
+    let (old_admin, effective_at): (Option<Address>, u64) = data.into_val(&env);
+    assert_eq!((old_admin, effective_at), (Some(admin), 4_600));
+}
 
 #[test]
-fn test() {
+fn test_lock_without_admin() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, HelloContract);
-    let client = HelloContractClient::new(&env, &contract_id);
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, LockAndReleaseContract);
+    let client = LockAndReleaseContractClient::new(&env, &contract_id);
+    client.initialize(&Address::generate(&env));
+
+    let token = create_token(&env);
+    let res = client.try_lock(
+        &Address::generate(&env),
+        &token,
+        &String::from_str(&env, "USDC"),
+        &token,
+        &100,
+        &ETHEREUM,
+        &recipient(&env),
+    );
+    assert_eq!(res, Err(Ok(LockReleaseError::AdminNotSet)));
+    assert_eq!(
+        client.try_release(
+            &100,
+            &Address::generate(&env),
+            &token,
+            &source_transfer(&env, 1)
+        ),
+        Err(Ok(LockReleaseError::AdminNotSet))
+    );
+}
+
+#[test]
+fn test_lock_invalid_amounts() {
+    let s = setup();
+    assert_eq!(lock(&s, 0), Err(LockReleaseError::InvalidAmount));
+    assert_eq!(lock(&s, -5), Err(LockReleaseError::InvalidAmount));
+}
+
+#[test]
+fn test_release_insufficient_admin_balance() {
+    let s = setup();
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.admin, &50);
+
+    assert_eq!(
+        s.client
+            .try_release(&100, &s.user, &s.token, &source_transfer(&s.env, 1)),
+        Err(Ok(LockReleaseError::InsufficientAdminBalance))
+    );
+    assert_eq!(
+        s.client
+            .try_release(&0, &s.user, &s.token, &source_transfer(&s.env, 1)),
+        Err(Ok(LockReleaseError::InvalidAmount))
+    );
+}
+
+#[test]
+fn test_lock_and_release() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+
+    assert_eq!(lock(&s, 1_000), Ok(0));
+    assert_eq!(token.balance(&s.user), 1_000_000_000 - 1_000);
+    assert_eq!(token.balance(&s.admin), 970);
+    assert_eq!(token.balance(&s.client.address), 30);
+
+    s.client
+        .release(&500, &s.user, &s.token, &source_transfer(&s.env, 1));
+    assert_eq!(token.balance(&s.admin), 470);
+}
+
+#[test]
+fn test_set_fee_bps() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    assert_eq!(s.client.get_fee_bps(), DEFAULT_FEE_BPS);
+
+    s.client.set_fee_bps(&50);
+    assert_eq!(s.client.get_fee_bps(), 50);
+    assert_eq!(
+        s.client.try_set_fee_bps(&(MAX_FEE_BPS + 1)),
+        Err(Ok(LockReleaseError::FeeTooHigh))
+    );
+
+    assert_eq!(lock(&s, 10_000), Ok(0));
+    assert_eq!(token.balance(&s.admin), 9_950);
+    assert_eq!(token.balance(&s.client.address), 50);
+}
+
+#[test]
+fn test_token_fee_override() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    let other_token = create_token(&s.env);
+
+    s.client.set_token_fee_bps(&s.token, &10);
+    assert_eq!(s.client.get_token_fee_bps(&s.token), 10);
+    assert_eq!(s.client.get_token_fee_bps(&other_token), DEFAULT_FEE_BPS);
+    assert_eq!(
+        s.client.try_set_token_fee_bps(&s.token, &(MAX_FEE_BPS + 1)),
+        Err(Ok(LockReleaseError::FeeTooHigh))
+    );
+
+    assert_eq!(lock(&s, 10_000), Ok(0));
+    assert_eq!(token.balance(&s.admin), 9_990);
+
+    // Clearing the override falls back to the global fee
+    s.client.set_fee_bps(&100);
+    s.client.clear_token_fee_bps(&s.token);
+    assert_eq!(s.client.get_token_fee_bps(&s.token), 100);
+}
+
+#[test]
+fn test_revenue_swept_per_token() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    let revenue = Address::generate(&s.env);
+    s.client.set_revenue_address(&revenue);
+
+    // No threshold configured: revenue accumulates in the contract
+    assert_eq!(s.client.get_sweep_threshold(&s.token), None);
+    assert_eq!(lock(&s, 1_000), Ok(0));
+    assert_eq!(s.client.get_accumulated_revenue(&s.token), 30);
+
+    assert_eq!(
+        s.client.try_set_sweep_threshold(&s.token, &0),
+        Err(Ok(LockReleaseError::InvalidThreshold))
+    );
+    s.client.set_sweep_threshold(&s.token, &50);
+    assert_eq!(s.client.get_sweep_threshold(&s.token), Some(50));
+
+    // Revenue in another token is tracked separately
+    let other_token = create_token(&s.env);
+    assert_eq!(s.client.get_accumulated_revenue(&other_token), 0);
+
+    assert_eq!(lock(&s, 1_000), Ok(1));
+    assert_eq!(token.balance(&revenue), 60);
+    assert_eq!(s.client.get_accumulated_revenue(&s.token), 0);
+}
+
+#[test]
+fn test_sweep_revenue() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    assert_eq!(lock(&s, 1_000), Ok(0));
+    assert_eq!(
+        s.client.try_sweep_revenue(&s.token),
+        Err(Ok(LockReleaseError::RevenueNotSet))
+    );
+
+    // Revenue below any threshold can still be withdrawn
+    let revenue = Address::generate(&s.env);
+    s.client.set_revenue_address(&revenue);
+    assert_eq!(s.client.sweep_revenue(&s.token), 30);
+    assert_eq!(token.balance(&revenue), 30);
+    assert_eq!(s.client.get_accumulated_revenue(&s.token), 0);
+    assert_eq!(s.client.sweep_revenue(&s.token), 0);
+}
+
+#[test]
+fn test_lock_ids_are_unique() {
+    let s = setup();
+    assert_eq!(lock(&s, 1_000), Ok(0));
+    assert_eq!(lock(&s, 2_000), Ok(1));
+
+    let first = s.client.get_lock(&0);
+    let second = s.client.get_lock(&1);
+    assert_eq!(first.user_address, s.user);
+    assert_eq!(first.in_amount, 1_000);
+    assert_eq!(second.in_amount, 2_000);
+    assert_eq!(
+        s.client.try_get_lock(&2),
+        Err(Ok(LockReleaseError::LockNotFound))
+    );
+}
+
+#[test]
+fn test_release_rejects_replay() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.admin, &1_000);
+
+    let transfer = source_transfer(&s.env, 7);
+    assert!(!s.client.is_processed(&transfer));
+    s.client.release(&100, &s.user, &s.token, &transfer);
+    assert!(s.client.is_processed(&transfer));
+
+    assert_eq!(
+        s.client.try_release(&100, &s.user, &s.token, &transfer),
+        Err(Ok(LockReleaseError::AlreadyProcessed))
+    );
+    assert_eq!(token.balance(&s.admin), 900);
+
+    // Same hash from a different source chain is a distinct transfer
+    let other_chain = SourceTransfer {
+        chain_id: 56,
+        ..transfer
+    };
+    s.client.release(&100, &s.user, &s.token, &other_chain);
+    assert_eq!(token.balance(&s.admin), 800);
+}
+
+#[test]
+fn test_release_emits_event() {
+    let s = setup();
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.admin, &1_000);
+
+    let transfer = source_transfer(&s.env, 3);
+    s.client.release(&250, &s.user, &s.token, &transfer);
+
+    let (contract, topics, data) = s.env.events().all().last().unwrap();
+    assert_eq!(contract, s.client.address);
+    assert_eq!(
+        topics,
+        vec![
+            &s.env,
+            Symbol::new(&s.env, "Release").into_val(&s.env),
+            s.user.into_val(&s.env),
+        ]
+    );
+    let event: ReleaseEvent = data.into_val(&s.env);
+    assert_eq!(
+        event,
+        ReleaseEvent {
+            user: s.user.clone(),
+            token: s.token.clone(),
+            amount: 250,
+            admin: s.admin.clone(),
+            source_transfer: transfer,
+        }
+    );
+}
+
+#[test]
+fn test_lock_emits_event() {
+    let s = setup();
+    let lock_id = lock(&s, 1_000).unwrap();
+
+    let (contract, topics, data) = s.env.events().all().last().unwrap();
+    assert_eq!(contract, s.client.address);
+    assert_eq!(
+        topics,
+        vec![
+            &s.env,
+            Symbol::new(&s.env, "Lock").into_val(&s.env),
+            s.user.into_val(&s.env),
+            s.token.into_val(&s.env),
+        ]
+    );
+    let event: LockEvent = data.into_val(&s.env);
+    assert_eq!(
+        event,
+        LockEvent {
+            lock_id,
+            user_address: s.user.clone(),
+            from_token: s.token.clone(),
+            src_token: s.token.clone(),
+            dest_token: String::from_str(&s.env, "USDC"),
+            in_amount: 1_000,
+            swaped_amount: 970,
+            recipient_address: recipient(&s.env),
+            dest_chain: ETHEREUM,
+        }
+    );
+}
+
+#[test]
+fn test_escrow_custody() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    assert_eq!(
+        s.client.get_custody_mode(&s.token),
+        CustodyMode::ForwardToAdmin
+    );
+
+    s.client.set_custody_mode(&s.token, &CustodyMode::Escrow);
+    assert_eq!(lock(&s, 1_000), Ok(0));
+    assert_eq!(token.balance(&s.admin), 0);
+    assert_eq!(token.balance(&s.client.address), 1_000);
+    assert_eq!(s.client.get_escrow_balance(&s.token), 970);
+
+    // Escrowed releases are paid from the contract, never from revenue
+    assert_eq!(
+        s.client
+            .try_release(&971, &s.user, &s.token, &source_transfer(&s.env, 1)),
+        Err(Ok(LockReleaseError::InsufficientEscrowBalance))
+    );
+    let recipient = Address::generate(&s.env);
+    s.client
+        .release(&900, &recipient, &s.token, &source_transfer(&s.env, 1));
+    assert_eq!(token.balance(&recipient), 900);
+    assert_eq!(s.client.get_escrow_balance(&s.token), 70);
+    assert_eq!(token.balance(&s.client.address), 100);
+
+    // Escrow can only be left once it has been paid out
+    assert_eq!(
+        s.client
+            .try_set_custody_mode(&s.token, &CustodyMode::ForwardToAdmin),
+        Err(Ok(LockReleaseError::EscrowNotEmpty))
+    );
+    s.client
+        .release(&70, &recipient, &s.token, &source_transfer(&s.env, 2));
+    s.client
+        .set_custody_mode(&s.token, &CustodyMode::ForwardToAdmin);
+}
+
+#[test]
+fn test_refund_after_expiry() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    s.client.set_refund_expiry(&3_600);
+    s.client.set_custody_mode(&s.token, &CustodyMode::Escrow);
+
+    let lock_id = lock(&s, 1_000).unwrap();
+    assert_eq!(s.client.get_lock(&lock_id).status, LockStatus::Pending);
+    assert_eq!(
+        s.client.try_refund(&lock_id),
+        Err(Ok(LockReleaseError::RefundNotAvailable))
+    );
+
+    s.env.ledger().with_mut(|li| li.timestamp += 3_600);
+    s.client.refund(&lock_id);
+    assert_eq!(token.balance(&s.user), 1_000_000_000 - 30);
+    assert_eq!(s.client.get_escrow_balance(&s.token), 0);
+    assert_eq!(s.client.get_lock(&lock_id).status, LockStatus::Refunded);
+    assert_eq!(
+        s.client.try_refund(&lock_id),
+        Err(Ok(LockReleaseError::LockNotPending))
+    );
+
+    // An expiry that overflows the lock timestamp never elapses
+    s.client.set_refund_expiry(&u64::MAX);
+    let lock_id = lock(&s, 1_000).unwrap();
+    assert_eq!(
+        s.client.try_refund(&lock_id),
+        Err(Ok(LockReleaseError::RefundNotAvailable))
+    );
+}
+
+#[test]
+fn test_refund_pulls_from_admin() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    let lock_id = lock(&s, 1_000).unwrap();
+    s.env
+        .ledger()
+        .with_mut(|li| li.timestamp += DEFAULT_REFUND_EXPIRY);
+
+    assert_eq!(
+        s.client.try_refund(&lock_id),
+        Err(Ok(LockReleaseError::InsufficientAdminAllowance))
+    );
+    token.approve(&s.admin, &s.client.address, &970, &1_000);
+    s.client.refund(&lock_id);
+    assert_eq!(token.balance(&s.admin), 0);
+    assert_eq!(token.balance(&s.user), 1_000_000_000 - 30);
+}
+
+#[test]
+fn test_fulfilled_lock_cannot_be_refunded() {
+    let s = setup();
+    let lock_id = lock(&s, 1_000).unwrap();
+    s.client.mark_fulfilled(&lock_id);
+    assert_eq!(s.client.get_lock(&lock_id).status, LockStatus::Fulfilled);
+
+    s.env
+        .ledger()
+        .with_mut(|li| li.timestamp += DEFAULT_REFUND_EXPIRY);
+    assert_eq!(
+        s.client.try_refund(&lock_id),
+        Err(Ok(LockReleaseError::LockNotPending))
+    );
+    assert_eq!(
+        s.client.try_mark_fulfilled(&lock_id),
+        Err(Ok(LockReleaseError::LockNotPending))
+    );
+}
+
+#[test]
+fn test_pause_blocks_lock_and_release() {
+    let s = setup();
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.admin, &1_000);
+    let pauser = Address::generate(&s.env);
+
+    assert_eq!(
+        s.client.try_set_paused(&pauser, &true),
+        Err(Ok(LockReleaseError::Unauthorized))
+    );
+    s.client.set_pauser(&pauser);
+    s.client.set_paused(&pauser, &true);
+    assert!(s.client.is_paused());
+    assert_eq!(lock(&s, 1_000), Err(LockReleaseError::ContractPaused));
+    assert_eq!(
+        s.client
+            .try_release(&100, &s.user, &s.token, &source_transfer(&s.env, 1)),
+        Err(Ok(LockReleaseError::ContractPaused))
+    );
+
+    s.client.set_paused(&s.owner, &false);
+    assert_eq!(lock(&s, 1_000), Ok(0));
+}
+
+#[test]
+fn test_token_and_chain_pause() {
+    let s = setup();
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.admin, &1_000);
+
+    s.client.set_token_paused(&s.owner, &s.token, &true);
+    assert!(s.client.is_token_paused(&s.token));
+    assert_eq!(lock(&s, 1_000), Err(LockReleaseError::TokenPaused));
+    assert_eq!(
+        s.client
+            .try_release(&100, &s.user, &s.token, &source_transfer(&s.env, 1)),
+        Err(Ok(LockReleaseError::TokenPaused))
+    );
+    s.client.set_token_paused(&s.owner, &s.token, &false);
+
+    s.client.set_chain_paused(&s.owner, &ETHEREUM, &true);
+    assert!(s.client.is_chain_paused(&ETHEREUM));
+    assert_eq!(lock(&s, 1_000), Err(LockReleaseError::ChainPaused));
+    s.client
+        .release(&100, &s.user, &s.token, &source_transfer(&s.env, 1));
+}
+
+#[test]
+fn test_migrate_legacy_revenue() {
+    let s = setup();
+    assert_eq!(s.client.get_storage_version(), STORAGE_VERSION);
+    assert_eq!(
+        s.client.try_migrate(&s.token),
+        Err(Ok(LockReleaseError::AlreadyMigrated))
+    );
+
+    // Recreate the version 0 layout: no version entry, shared revenue
+    s.env.as_contract(&s.client.address, || {
+        let storage = s.env.storage().instance();
+        storage.remove(&DataKey::StorageVersion);
+        storage.set(&LegacyDataKey::AccumulatedRevenue, &1_234_i128);
+    });
+    assert_eq!(s.client.get_storage_version(), 0);
+
+    s.client.migrate(&s.token);
+    assert_eq!(s.client.get_storage_version(), STORAGE_VERSION);
+    assert_eq!(s.client.get_accumulated_revenue(&s.token), 1_234);
+    assert_eq!(s.client.get_sweep_threshold(&s.token), Some(100_000_000));
+    s.env.as_contract(&s.client.address, || {
+        assert!(!s
+            .env
+            .storage()
+            .instance()
+            .has(&LegacyDataKey::AccumulatedRevenue));
+    });
+}
+
+#[test]
+fn test_two_step_ownership_transfer() {
+    let s = setup();
+    let new_owner = Address::generate(&s.env);
+
+    assert_eq!(
+        s.client.try_accept_ownership(),
+        Err(Ok(LockReleaseError::NoPendingOwner))
+    );
+    s.client.propose_owner(&new_owner);
+    assert_eq!(s.client.get_pending_owner(), Some(new_owner.clone()));
+    assert_eq!(s.client.get_owner(), s.owner);
+
+    s.client.cancel_ownership_transfer();
+    assert_eq!(s.client.get_pending_owner(), None);
+
+    s.client.propose_owner(&new_owner);
+    s.client.accept_ownership();
+    assert_eq!(
+        s.env.auths()[0].0,
+        new_owner,
+        "accept_ownership must be signed by the new owner"
+    );
+    assert_eq!(s.client.get_owner(), new_owner);
+    assert_eq!(s.client.get_pending_owner(), None);
+}
+
+#[test]
+fn test_renounce_ownership() {
+    let s = setup();
+    let pauser = Address::generate(&s.env);
+    s.client.set_pauser(&pauser);
+
+    s.client.renounce_ownership();
+    assert_eq!(
+        s.client.try_get_owner(),
+        Err(Ok(LockReleaseError::OwnershipRenounced))
+    );
+    assert_eq!(
+        s.client.try_set_fee_bps(&100),
+        Err(Ok(LockReleaseError::OwnershipRenounced))
+    );
+
+    // The pauser can still halt the contract
+    s.client.set_paused(&pauser, &true);
+    assert!(s.client.is_paused());
+}
+
+#[test]
+fn test_relayer_threshold_release() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.admin, &1_000);
+    token.approve(&s.admin, &s.client.address, &1_000, &1_000);
 
-    let words = client.hello(&symbol_short!("Dev"));
+    let relayers = [
+        Address::generate(&s.env),
+        Address::generate(&s.env),
+        Address::generate(&s.env),
+    ];
+    for relayer in relayers.iter() {
+        s.client.add_relayer(relayer);
+    }
     assert_eq!(
-        words,
-        vec![&env, symbol_short!("Hello"), symbol_short!("Dev"),]
+        s.client.try_set_relayer_threshold(&4),
+        Err(Ok(LockReleaseError::InvalidThreshold))
     );
+    s.client.set_relayer_threshold(&2);
+
+    // The admin can no longer release on its own
+    let transfer = source_transfer(&s.env, 9);
+    assert_eq!(
+        s.client.try_release(&100, &s.user, &s.token, &transfer),
+        Err(Ok(LockReleaseError::RelayerApprovalRequired))
+    );
+
+    let outsider = Address::generate(&s.env);
+    assert_eq!(
+        s.client
+            .try_approve_release(&outsider, &100, &s.user, &s.token, &transfer),
+        Err(Ok(LockReleaseError::NotRelayer))
+    );
+
+    assert!(!s
+        .client
+        .approve_release(&relayers[0], &100, &s.user, &s.token, &transfer));
+    assert_eq!(
+        s.client
+            .try_approve_release(&relayers[0], &100, &s.user, &s.token, &transfer),
+        Err(Ok(LockReleaseError::AlreadyApproved))
+    );
+    // A conflicting claim is counted separately instead of blocking others
+    assert!(!s
+        .client
+        .approve_release(&relayers[1], &999, &s.user, &s.token, &transfer));
+    assert_eq!(token.balance(&s.user), 1_000_000_000);
+
+    assert!(s
+        .client
+        .approve_release(&relayers[1], &100, &s.user, &s.token, &transfer));
+    assert_eq!(token.balance(&s.user), 1_000_000_100);
+    assert!(s.client.is_processed(&transfer));
+    assert_eq!(
+        s.client
+            .get_release_request(&100, &s.user, &s.token, &transfer),
+        None
+    );
+    assert_eq!(
+        s.client
+            .try_approve_release(&relayers[2], &999, &s.user, &s.token, &transfer),
+        Err(Ok(LockReleaseError::AlreadyProcessed))
+    );
+}
+
+#[test]
+fn test_relayer_release_held_by_breaker() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.admin, &1_000);
+    token.approve(&s.admin, &s.client.address, &1_000, &1_000);
+    let relayers = [Address::generate(&s.env), Address::generate(&s.env)];
+    for relayer in relayers.iter() {
+        s.client.add_relayer(relayer);
+    }
+    s.client.set_relayer_threshold(&2);
+    s.client.set_outflow_breaker(&s.token, &50, &0);
+
+    let transfer = source_transfer(&s.env, 9);
+    assert!(!s
+        .client
+        .approve_release(&relayers[0], &100, &s.user, &s.token, &transfer));
+    assert!(!s
+        .client
+        .approve_release(&relayers[1], &100, &s.user, &s.token, &transfer));
+    assert!(s.client.is_outflow_tripped(&s.token));
+    assert_eq!(
+        s.client
+            .get_release_request(&100, &s.user, &s.token, &transfer)
+            .unwrap()
+            .approvals
+            .len(),
+        2
+    );
+
+    // The approvals survive the trip, so any approver can retry
+    s.client.set_outflow_breaker(&s.token, &1_000, &0);
+    s.client.reset_outflow_breaker(&s.token);
+    assert!(s
+        .client
+        .approve_release(&relayers[0], &100, &s.user, &s.token, &transfer));
+    assert_eq!(token.balance(&s.user), 1_000_000_100);
+}
+
+#[test]
+fn test_remove_relayer_keeps_threshold_reachable() {
+    let s = setup();
+    let relayer = Address::generate(&s.env);
+    s.client.add_relayer(&relayer);
+    s.client.set_relayer_threshold(&1);
+
+    assert_eq!(
+        s.client.try_remove_relayer(&relayer),
+        Err(Ok(LockReleaseError::InvalidThreshold))
+    );
+    s.client.set_relayer_threshold(&0);
+    s.client.remove_relayer(&relayer);
+    assert_eq!(s.client.get_relayers().len(), 0);
+}
+
+fn sign_payload(env: &Env, key: &SigningKey, payload: &ReleasePayload) -> ValidatorSignature {
+    let message = payload.clone().to_xdr(env);
+    let mut buf = [0u8; 512];
+    let len = message.len() as usize;
+    message.copy_into_slice(&mut buf[..len]);
+    ValidatorSignature {
+        public_key: BytesN::from_array(env, &key.verifying_key().to_bytes()),
+        signature: BytesN::from_array(env, &key.sign(&buf[..len]).to_bytes()),
+    }
+}
+
+#[test]
+fn test_release_with_signatures() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    s.client.set_custody_mode(&s.token, &CustodyMode::Escrow);
+    lock(&s, 1_000).unwrap();
+
+    let keys = [
+        SigningKey::from_bytes(&[1; 32]),
+        SigningKey::from_bytes(&[2; 32]),
+        SigningKey::from_bytes(&[3; 32]),
+    ];
+    let mut public_keys = Vec::new(&s.env);
+    for key in keys.iter() {
+        public_keys.push_back(BytesN::from_array(&s.env, &key.verifying_key().to_bytes()));
+    }
+    let mut duplicated = public_keys.clone();
+    duplicated.push_back(public_keys.get(0).unwrap());
+    assert_eq!(
+        s.client.try_set_validator_set(&duplicated, &4),
+        Err(Ok(LockReleaseError::DuplicateValidator))
+    );
+    assert_eq!(s.client.set_validator_set(&public_keys, &2), 0);
+
+    let recipient = Address::generate(&s.env);
+    let payload = ReleasePayload {
+        contract: s.client.address.clone(),
+        epoch: 0,
+        user: recipient.clone(),
+        token: s.token.clone(),
+        amount: 500,
+        source_transfer: source_transfer(&s.env, 4),
+    };
+    let first = sign_payload(&s.env, &keys[0], &payload);
+    let second = sign_payload(&s.env, &keys[1], &payload);
+
+    assert_eq!(
+        s.client
+            .try_release_with_signatures(&payload, &vec![&s.env, first.clone()]),
+        Err(Ok(LockReleaseError::InsufficientSignatures))
+    );
+    assert_eq!(
+        s.client
+            .try_release_with_signatures(&payload, &vec![&s.env, first.clone(), first.clone()]),
+        Err(Ok(LockReleaseError::DuplicateSignature))
+    );
+    let outsider = sign_payload(&s.env, &SigningKey::from_bytes(&[9; 32]), &payload);
+    assert_eq!(
+        s.client
+            .try_release_with_signatures(&payload, &vec![&s.env, first.clone(), outsider]),
+        Err(Ok(LockReleaseError::UnknownValidator))
+    );
+
+    s.client
+        .release_with_signatures(&payload, &vec![&s.env, first.clone(), second.clone()]);
+    assert_eq!(token.balance(&recipient), 500);
+    assert_eq!(
+        s.client
+            .try_release_with_signatures(&payload, &vec![&s.env, first, second]),
+        Err(Ok(LockReleaseError::AlreadyProcessed))
+    );
+
+    // Rotating the set invalidates signatures from the previous epoch
+    assert_eq!(s.client.set_validator_set(&public_keys, &2), 1);
+    let stale = ReleasePayload {
+        source_transfer: source_transfer(&s.env, 5),
+        ..payload
+    };
+    let signatures = vec![
+        &s.env,
+        sign_payload(&s.env, &keys[0], &stale),
+        sign_payload(&s.env, &keys[1], &stale),
+    ];
+    assert_eq!(
+        s.client.try_release_with_signatures(&stale, &signatures),
+        Err(Ok(LockReleaseError::StaleEpoch))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_release_with_forged_signature() {
+    let s = setup();
+    let key = SigningKey::from_bytes(&[1; 32]);
+    s.client.set_validator_set(
+        &vec![
+            &s.env,
+            BytesN::from_array(&s.env, &key.verifying_key().to_bytes()),
+        ],
+        &1,
+    );
+
+    let payload = ReleasePayload {
+        contract: s.client.address.clone(),
+        epoch: 0,
+        user: s.user.clone(),
+        token: s.token.clone(),
+        amount: 500,
+        source_transfer: source_transfer(&s.env, 4),
+    };
+    let mut signature = sign_payload(&s.env, &key, &payload);
+    signature.signature = BytesN::from_array(&s.env, &[0; 64]);
+    s.client
+        .release_with_signatures(&payload, &vec![&s.env, signature]);
+}
+
+fn evm_address(env: &Env, key: &EvmSigningKey) -> BytesN<20> {
+    let point = key.verifying_key().to_encoded_point(false);
+    let hash = env
+        .crypto()
+        .keccak256(&Bytes::from_slice(env, &point.as_bytes()[1..]))
+        .to_array();
+    BytesN::from_array(env, &hash[12..].try_into().unwrap())
+}
+
+fn evm_sign_payload(env: &Env, key: &EvmSigningKey, payload: &ReleasePayload) -> EvmSignature {
+    let payload_hash = env.crypto().keccak256(&payload.clone().to_xdr(env));
+    let mut message = Bytes::from_slice(env, b"\x19Ethereum Signed Message:\n32");
+    message.append(&payload_hash.to_bytes().into());
+    let digest = env.crypto().keccak256(&message).to_array();
+
+    let (signature, recovery_id) = key.sign_prehash_recoverable(&digest).unwrap();
+    EvmSignature {
+        signature: BytesN::from_array(env, &signature.to_bytes().into()),
+        recovery_id: 27 + recovery_id.to_byte() as u32,
+    }
+}
+
+#[test]
+fn test_release_with_evm_signatures() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    s.client.set_custody_mode(&s.token, &CustodyMode::Escrow);
+    lock(&s, 1_000).unwrap();
+
+    let keys = [
+        EvmSigningKey::from_slice(&[1; 32]).unwrap(),
+        EvmSigningKey::from_slice(&[2; 32]).unwrap(),
+    ];
+    let signers = vec![
+        &s.env,
+        evm_address(&s.env, &keys[0]),
+        evm_address(&s.env, &keys[1]),
+    ];
+    let mut duplicated = signers.clone();
+    duplicated.push_back(signers.get(0).unwrap());
+    assert_eq!(
+        s.client.try_set_evm_validator_set(&duplicated, &2),
+        Err(Ok(LockReleaseError::DuplicateValidator))
+    );
+    assert_eq!(s.client.set_evm_validator_set(&signers, &2), 0);
+
+    let recipient = Address::generate(&s.env);
+    let payload = ReleasePayload {
+        contract: s.client.address.clone(),
+        epoch: 0,
+        user: recipient.clone(),
+        token: s.token.clone(),
+        amount: 500,
+        source_transfer: source_transfer(&s.env, 6),
+    };
+    let first = evm_sign_payload(&s.env, &keys[0], &payload);
+    let second = evm_sign_payload(&s.env, &keys[1], &payload);
+
+    assert_eq!(
+        s.client
+            .try_release_with_evm_signatures(&payload, &vec![&s.env, first.clone()]),
+        Err(Ok(LockReleaseError::InsufficientSignatures))
+    );
+    assert_eq!(
+        s.client
+            .try_release_with_evm_signatures(&payload, &vec![&s.env, first.clone(), first.clone()]),
+        Err(Ok(LockReleaseError::DuplicateSignature))
+    );
+    let outsider = evm_sign_payload(
+        &s.env,
+        &EvmSigningKey::from_slice(&[9; 32]).unwrap(),
+        &payload,
+    );
+    assert_eq!(
+        s.client
+            .try_release_with_evm_signatures(&payload, &vec![&s.env, first.clone(), outsider]),
+        Err(Ok(LockReleaseError::UnknownValidator))
+    );
+    let bad_recovery_id = EvmSignature {
+        recovery_id: 5,
+        ..first.clone()
+    };
+    assert_eq!(
+        s.client
+            .try_release_with_evm_signatures(&payload, &vec![&s.env, bad_recovery_id]),
+        Err(Ok(LockReleaseError::InvalidRecoveryId))
+    );
+
+    s.client
+        .release_with_evm_signatures(&payload, &vec![&s.env, first, second]);
+    assert_eq!(token.balance(&recipient), 500);
+    assert!(s.client.is_processed(&payload.source_transfer));
+}
+
+fn hash_pair(env: &Env, a: &BytesN<32>, b: &BytesN<32>) -> BytesN<32> {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut pair = Bytes::from(first.clone());
+    pair.append(&Bytes::from(second.clone()));
+    env.crypto().sha256(&pair).to_bytes()
+}
+
+#[test]
+fn test_claim_from_release_batch() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    s.client.set_custody_mode(&s.token, &CustodyMode::Escrow);
+    lock(&s, 1_000).unwrap();
+
+    let leaves: [ReleaseLeaf; 3] = core::array::from_fn(|i| ReleaseLeaf {
+        user: Address::generate(&s.env),
+        token: s.token.clone(),
+        amount: 100 * (i as i128 + 1),
+        source_transfer: source_transfer(&s.env, 10 + i as u8),
+    });
+    let hashes: [BytesN<32>; 3] = core::array::from_fn(|i| hash_release_leaf(&s.env, &leaves[i]));
+    let node = hash_pair(&s.env, &hashes[0], &hashes[1]);
+    let root = hash_pair(&s.env, &node, &hashes[2]);
+
+    assert_eq!(
+        s.client
+            .try_claim(&7, &leaves[2], &vec![&s.env, node.clone()]),
+        Err(Ok(LockReleaseError::BatchNotFound))
+    );
+    s.client.post_release_root(&7, &root);
+    assert_eq!(s.client.get_release_root(&7), Some(root.clone()));
+    assert_eq!(
+        s.client.try_post_release_root(&7, &node),
+        Err(Ok(LockReleaseError::BatchAlreadyPosted))
+    );
+
+    let proof = vec![&s.env, hashes[1].clone(), hashes[2].clone()];
+    let mut forged = leaves[0].clone();
+    forged.amount = 900;
+    assert_eq!(
+        s.client.try_claim(&7, &forged, &proof),
+        Err(Ok(LockReleaseError::InvalidProof))
+    );
+
+    s.client.claim(&7, &leaves[0], &proof);
+    s.client.claim(&7, &leaves[2], &vec![&s.env, node]);
+    assert_eq!(token.balance(&leaves[0].user), 100);
+    assert_eq!(token.balance(&leaves[2].user), 300);
+    assert!(s.client.is_claimed(&7, &leaves[0]));
+    assert!(!s.client.is_claimed(&7, &leaves[1]));
+    assert_eq!(
+        s.client.try_claim(&7, &leaves[0], &proof),
+        Err(Ok(LockReleaseError::AlreadyProcessed))
+    );
+}
+
+#[test]
+fn test_token_allowlist() {
+    let s = setup();
+    let other_token = create_token(&s.env);
+    assert_eq!(
+        s.client.get_supported_tokens(),
+        vec![&s.env, s.token.clone()]
+    );
+    assert_eq!(
+        s.client.get_token_info(&s.token),
+        Some(TokenInfo {
+            decimals: 7,
+            symbol: String::from_str(&s.env, "USDC"),
+            enabled: true,
+        })
+    );
+    assert_eq!(s.client.get_token_info(&other_token), None);
+
+    let res = s.client.try_lock(
+        &s.user,
+        &other_token,
+        &String::from_str(&s.env, "USDC"),
+        &other_token,
+        &1_000,
+        &ETHEREUM,
+        &recipient(&s.env),
+    );
+    assert_eq!(res, Err(Ok(LockReleaseError::TokenNotSupported)));
+
+    // Disabled tokens stay registered but are rejected by lock
+    s.client
+        .set_supported_token(&s.token, &7, &String::from_str(&s.env, "USDC"), &false);
+    assert_eq!(s.client.get_supported_tokens(), vec![&s.env]);
+    assert_eq!(lock(&s, 1_000), Err(LockReleaseError::TokenNotSupported));
+
+    s.client
+        .set_supported_token(&s.token, &7, &String::from_str(&s.env, "USDC"), &true);
+    assert_eq!(lock(&s, 1_000), Ok(0));
+
+    s.client.remove_supported_token(&s.token);
+    assert_eq!(s.client.get_token_info(&s.token), None);
+    assert_eq!(lock(&s, 1_000), Err(LockReleaseError::TokenNotSupported));
+    assert_eq!(
+        s.client.try_remove_supported_token(&s.token),
+        Err(Ok(LockReleaseError::TokenNotSupported))
+    );
+}
+
+#[test]
+fn test_chain_registry() {
+    let s = setup();
+    let polygon = 137;
+    assert_eq!(s.client.get_active_chains(), vec![&s.env, ETHEREUM]);
+    assert_eq!(s.client.get_chain_info(&polygon), None);
+
+    let res = s.client.try_lock(
+        &s.user,
+        &s.token,
+        &String::from_str(&s.env, "USDC"),
+        &s.token,
+        &1_000,
+        &polygon,
+        &recipient(&s.env),
+    );
+    assert_eq!(res, Err(Ok(LockReleaseError::ChainNotSupported)));
+
+    s.client.set_supported_chain(
+        &polygon,
+        &String::from_str(&s.env, "Polygon"),
+        &AddressFormat::Evm,
+        &true,
+    );
+    assert_eq!(
+        s.client.get_active_chains(),
+        vec![&s.env, ETHEREUM, polygon]
+    );
+
+    // Disabled chains stay registered but are rejected by lock
+    s.client.set_supported_chain(
+        &ETHEREUM,
+        &String::from_str(&s.env, "Ethereum"),
+        &AddressFormat::Evm,
+        &false,
+    );
+    assert_eq!(s.client.get_active_chains(), vec![&s.env, polygon]);
+    assert!(!s.client.get_chain_info(&ETHEREUM).unwrap().enabled);
+    assert_eq!(lock(&s, 1_000), Err(LockReleaseError::ChainNotSupported));
+
+    s.client.remove_supported_chain(&ETHEREUM);
+    assert_eq!(s.client.get_chain_info(&ETHEREUM), None);
+    assert_eq!(
+        s.client.try_remove_supported_chain(&ETHEREUM),
+        Err(Ok(LockReleaseError::ChainNotSupported))
+    );
+}
+
+#[test]
+fn test_recipient_validation() {
+    let s = setup();
+    let raw = |value: &[u8]| Recipient::Raw(Bytes::from_slice(&s.env, value));
+
+    // Hex strings are accepted for EVM chains and stored as typed addresses
+    let lock_id = lock_to(
+        &s,
+        1_000,
+        ETHEREUM,
+        raw(b"0x00000000000000000000000000000000000000Ab"),
+    )
+    .unwrap();
+    let mut address = [0; 20];
+    address[19] = 0xab;
+    assert_eq!(
+        s.client.get_lock(&lock_id).recipient_address,
+        Recipient::Evm(BytesN::from_array(&s.env, &address))
+    );
+    assert!(lock_to(
+        &s,
+        1_000,
+        ETHEREUM,
+        raw(b"00000000000000000000000000000000000000ab")
+    )
+    .is_ok());
+
+    for bad in [
+        raw(b"0x12"),
+        raw(b"0x00000000000000000000000000000000000000ag"),
+        raw(b"0y00000000000000000000000000000000000000ab"),
+        raw(b"0x00000000000000000000000000000000000000abcd"),
+        Recipient::Stellar(s.user.clone()),
+    ] {
+        assert_eq!(
+            lock_to(&s, 1_000, ETHEREUM, bad),
+            Err(LockReleaseError::InvalidRecipient)
+        );
+    }
+
+    let stellar = 2;
+    s.client.set_supported_chain(
+        &stellar,
+        &String::from_str(&s.env, "Stellar"),
+        &AddressFormat::Stellar,
+        &true,
+    );
+    s.client
+        .add_route(&s.token, &stellar, &String::from_str(&s.env, "USDC"));
+    assert_eq!(
+        lock_to(&s, 1_000, stellar, recipient(&s.env)),
+        Err(LockReleaseError::InvalidRecipient)
+    );
+    assert!(lock_to(&s, 1_000, stellar, Recipient::Stellar(s.user.clone())).is_ok());
+
+    let other = 3;
+    s.client.set_supported_chain(
+        &other,
+        &String::from_str(&s.env, "Other"),
+        &AddressFormat::Raw,
+        &true,
+    );
+    s.client
+        .add_route(&s.token, &other, &String::from_str(&s.env, "USDC"));
+    assert_eq!(
+        lock_to(&s, 1_000, other, raw(b"")),
+        Err(LockReleaseError::InvalidRecipient)
+    );
+    assert!(lock_to(&s, 1_000, other, raw(b"addr1")).is_ok());
+}
+
+#[test]
+fn test_token_routes() {
+    let s = setup();
+    let usdc = String::from_str(&s.env, "USDC");
+    let usdt = String::from_str(&s.env, "USDT");
+    let lock_dest = |dest_token: &String| {
+        s.client.try_lock(
+            &s.user,
+            &s.token,
+            dest_token,
+            &s.token,
+            &1_000,
+            &ETHEREUM,
+            &recipient(&s.env),
+        )
+    };
+
+    assert_eq!(
+        lock_dest(&usdt),
+        Err(Ok(LockReleaseError::RouteNotSupported))
+    );
+    s.client.add_route(&s.token, &ETHEREUM, &usdt);
+    assert_eq!(lock_dest(&usdt), Ok(Ok(0)));
+
+    let polygon = 137;
+    s.client.set_supported_chain(
+        &polygon,
+        &String::from_str(&s.env, "Polygon"),
+        &AddressFormat::Evm,
+        &true,
+    );
+    s.client.add_route(&s.token, &polygon, &usdc);
+    assert_eq!(
+        s.client.get_routes(&s.token),
+        vec![
+            &s.env,
+            Route {
+                dest_chain: ETHEREUM,
+                dest_tokens: vec![&s.env, usdc.clone(), usdt.clone()],
+            },
+            Route {
+                dest_chain: polygon,
+                dest_tokens: vec![&s.env, usdc.clone()],
+            },
+        ]
+    );
+    assert_eq!(s.client.get_routes(&create_token(&s.env)), vec![&s.env]);
+
+    s.client.remove_route(&s.token, &ETHEREUM, &usdc);
+    s.client.remove_route(&s.token, &ETHEREUM, &usdt);
+    assert_eq!(
+        lock_dest(&usdc),
+        Err(Ok(LockReleaseError::RouteNotSupported))
+    );
+    assert_eq!(
+        s.client.try_remove_route(&s.token, &ETHEREUM, &usdc),
+        Err(Ok(LockReleaseError::RouteNotSupported))
+    );
+    assert_eq!(
+        s.client.get_routes(&s.token),
+        vec![
+            &s.env,
+            Route {
+                dest_chain: polygon,
+                dest_tokens: vec![&s.env, usdc],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_rate_limits() {
+    let s = setup();
+    let other_user = Address::generate(&s.env);
+    StellarAssetClient::new(&s.env, &s.token).mint(&other_user, &10_000);
+    let lock_as = |user: &Address, amount: i128| {
+        s.client.try_lock(
+            user,
+            &s.token,
+            &String::from_str(&s.env, "USDC"),
+            &s.token,
+            &amount,
+            &ETHEREUM,
+            &recipient(&s.env),
+        )
+    };
+
+    assert_eq!(
+        s.client
+            .try_set_rate_limit(&s.token, &RateLimitKind::UserLock, &0, &3_600),
+        Err(Ok(LockReleaseError::InvalidThreshold))
+    );
+    s.client
+        .set_rate_limit(&s.token, &RateLimitKind::UserLock, &1_500, &3_600);
+    s.client
+        .set_rate_limit(&s.token, &RateLimitKind::TokenLock, &2_500, &3_600);
+    s.client
+        .set_rate_limit(&s.token, &RateLimitKind::TokenRelease, &500, &3_600);
+    assert_eq!(
+        s.client.get_rate_limit(&s.token, &RateLimitKind::UserLock),
+        Some(RateLimit {
+            max_amount: 1_500,
+            window: 3_600,
+        })
+    );
+
+    assert_eq!(lock(&s, 1_000), Ok(0));
+    assert_eq!(lock(&s, 600), Err(LockReleaseError::RateLimitExceeded));
+    assert_eq!(lock_as(&other_user, 1_000), Ok(Ok(1)));
+    // Per-user room is left, but the token's window is full
+    assert_eq!(
+        lock_as(&other_user, 501),
+        Err(Ok(LockReleaseError::RateLimitExceeded))
+    );
+
+    s.client
+        .release(&400, &s.user, &s.token, &source_transfer(&s.env, 1));
+    assert_eq!(
+        s.client
+            .try_release(&200, &s.user, &s.token, &source_transfer(&s.env, 2)),
+        Err(Ok(LockReleaseError::RateLimitExceeded))
+    );
+
+    // Two windows later the earlier volume no longer counts
+    s.env.ledger().with_mut(|li| li.timestamp += 7_200);
+    assert_eq!(lock(&s, 1_500), Ok(2));
+    s.client
+        .release(&200, &s.user, &s.token, &source_transfer(&s.env, 2));
+
+    s.client
+        .clear_rate_limit(&s.token, &RateLimitKind::UserLock);
+    assert_eq!(
+        s.client.get_rate_limit(&s.token, &RateLimitKind::UserLock),
+        None
+    );
+}
+
+#[test]
+fn test_outflow_breaker() {
+    let s = setup();
+    let token = token::Client::new(&s.env, &s.token);
+    StellarAssetClient::new(&s.env, &s.token).mint(&s.admin, &10_000);
+    let release = |amount: i128, n: u8| {
+        s.client
+            .try_release(&amount, &s.user, &s.token, &source_transfer(&s.env, n))
+    };
+
+    assert_eq!(
+        s.client.try_set_outflow_breaker(&s.token, &0, &1),
+        Err(Ok(LockReleaseError::InvalidThreshold))
+    );
+    s.client.set_outflow_breaker(&s.token, &1_000, &1);
+    assert_eq!(
+        s.client.get_outflow_breaker(&s.token),
+        Some(OutflowBreaker {
+            capacity: 1_000,
+            refill_per_second: 1,
+        })
+    );
+
+    assert_eq!(release(600, 1), Ok(Ok(true)));
+    let balance = token.balance(&s.user);
+
+    // A burst beyond the bucket trips the breaker instead of paying
+    assert_eq!(release(500, 2), Ok(Ok(false)));
+    let (_, topics, data) = s.env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        ("AnomalyDetected", s.token.clone()).into_val(&s.env)
+    );
+    let (amount, available): (i128, i128) = data.into_val(&s.env);
+    assert_eq!((amount, available), (500, 400));
+    assert_eq!(token.balance(&s.user), balance);
+    assert!(s.client.is_outflow_tripped(&s.token));
+    assert!(!s.client.is_processed(&source_transfer(&s.env, 2)));
+    assert_eq!(
+        release(1, 3),
+        Err(Ok(LockReleaseError::OutflowBreakerTripped))
+    );
+
+    s.client.reset_outflow_breaker(&s.token);
+    assert!(!s.client.is_outflow_tripped(&s.token));
+    assert_eq!(release(500, 2), Ok(Ok(true)));
+    assert_eq!(token.balance(&s.user), balance + 500);
+
+    // The bucket refills over time, up to its capacity
+    s.env.ledger().with_mut(|li| li.timestamp += 100);
+    assert_eq!(release(600, 3), Ok(Ok(true)));
+    assert!(!s.client.is_outflow_tripped(&s.token));
+
+    s.client.clear_outflow_breaker(&s.token);
+    assert_eq!(s.client.get_outflow_breaker(&s.token), None);
+    assert_eq!(release(5_000, 4), Ok(Ok(true)));
+}
+
+#[test]
+fn test_lock_limits() {
+    let s = setup();
+    assert_eq!(s.client.get_lock_limits(&s.token), None);
+    assert_eq!(
+        s.client.try_set_lock_limits(&s.token, &1_000, &999),
+        Err(Ok(LockReleaseError::InvalidThreshold))
+    );
+
+    s.client.set_lock_limits(&s.token, &1_000, &5_000);
+    assert_eq!(
+        s.client.get_lock_limits(&s.token),
+        Some(LockLimits {
+            min_amount: 1_000,
+            max_amount: 5_000,
+        })
+    );
+    assert_eq!(lock(&s, 999), Err(LockReleaseError::LockBelowMinimum));
+    assert_eq!(lock(&s, 5_001), Err(LockReleaseError::LockAboveMaximum));
+    assert_eq!(lock(&s, 1_000), Ok(0));
+    assert_eq!(lock(&s, 5_000), Ok(1));
+
+    s.client.clear_lock_limits(&s.token);
+    assert_eq!(s.client.get_lock_limits(&s.token), None);
+    assert_eq!(lock(&s, 10_000), Ok(2));
+}
+
+#[test]
+fn test_release_batches_respect_relayer_mode() {
+    let s = setup();
+    s.client.set_custody_mode(&s.token, &CustodyMode::Escrow);
+    lock(&s, 1_000).unwrap();
+
+    let leaf = ReleaseLeaf {
+        user: Address::generate(&s.env),
+        token: s.token.clone(),
+        amount: 900,
+        source_transfer: source_transfer(&s.env, 10),
+    };
+    let root = hash_release_leaf(&s.env, &leaf);
+    s.client.post_release_root(&1, &root);
+
+    let relayer = Address::generate(&s.env);
+    s.client.add_relayer(&relayer);
+    s.client.set_relayer_threshold(&1);
+
+    // The admin alone can neither post nor claim batches in relayer mode
+    assert_eq!(
+        s.client.try_post_release_root(&2, &root),
+        Err(Ok(LockReleaseError::RelayerApprovalRequired))
+    );
+    assert_eq!(
+        s.client.try_claim(&1, &leaf, &vec![&s.env]),
+        Err(Ok(LockReleaseError::RelayerApprovalRequired))
+    );
+    assert_eq!(s.client.get_escrow_balance(&s.token), 970);
+}
+
+#[test]
+fn test_rate_limit_window_boundary() {
+    let s = setup();
+    s.client
+        .set_rate_limit(&s.token, &RateLimitKind::TokenLock, &1_000, &3_600);
+
+    // Filling the limit just before a window boundary
+    s.env.ledger().with_mut(|li| li.timestamp = 3_599);
+    assert_eq!(lock(&s, 1_000), Ok(0));
+
+    // does not free any of it up until the lock leaves the last window
+    for timestamp in [3_600, 5_400, 7_198] {
+        s.env.ledger().with_mut(|li| li.timestamp = timestamp);
+        assert_eq!(lock(&s, 500), Err(LockReleaseError::RateLimitExceeded));
+        assert_eq!(lock(&s, 1), Err(LockReleaseError::RateLimitExceeded));
+    }
+    s.env.ledger().with_mut(|li| li.timestamp = 7_200);
+    assert_eq!(lock(&s, 600), Ok(1));
+
+    // Volume spread over the window adds up
+    s.env.ledger().with_mut(|li| li.timestamp = 9_000);
+    assert_eq!(lock(&s, 400), Ok(2));
+    assert_eq!(lock(&s, 1), Err(LockReleaseError::RateLimitExceeded));
+    s.env.ledger().with_mut(|li| li.timestamp = 11_100);
+    assert_eq!(lock(&s, 600), Ok(3));
+    assert_eq!(lock(&s, 1), Err(LockReleaseError::RateLimitExceeded));
+}
+
+const V0_WASM: &[u8] = include_bytes!("../testdata/lock_release_v0.wasm");
+
+fn assert_authorized_by(s: &Setup, signer: &Address, fn_name: &str, args: Vec<Val>) {
+    assert_eq!(
+        s.env.auths(),
+        std::vec![(
+            signer.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    s.client.address.clone(),
+                    Symbol::new(&s.env, fn_name),
+                    args,
+                )),
+                sub_invocations: std::vec![],
+            }
+        )]
+    );
+}
+
+#[test]
+fn test_migrate_and_upgrade_require_owner() {
+    let s = setup();
+    let outsider = Address::generate(&s.env);
+    let wasm_hash = s.env.deployer().upload_contract_wasm(V0_WASM);
+
+    // Only the owner's signature is accepted for migrate and upgrade
+    let migrate_args: Vec<Val> = (s.token.clone(),).into_val(&s.env);
+    let upgrade_args: Vec<Val> = (wasm_hash.clone(),).into_val(&s.env);
+    for (fn_name, args) in [("migrate", &migrate_args), ("upgrade", &upgrade_args)] {
+        s.env.mock_auths(&[MockAuth {
+            address: &outsider,
+            invoke: &MockAuthInvoke {
+                contract: &s.client.address,
+                fn_name,
+                args: args.clone(),
+                sub_invokes: &[],
+            },
+        }]);
+        let res = if fn_name == "migrate" {
+            s.client.try_migrate(&s.token).map(|_| ())
+        } else {
+            s.client.try_upgrade(&wasm_hash).map(|_| ())
+        };
+        // The call fails on authorization, before any contract error
+        assert!(matches!(res, Err(Err(_))));
+    }
+
+    s.env.mock_all_auths();
+    assert_eq!(
+        s.client.try_migrate(&s.token),
+        Err(Ok(LockReleaseError::AlreadyMigrated))
+    );
+
+    s.client.upgrade(&wasm_hash);
+    assert_authorized_by(&s, &s.owner, "upgrade", upgrade_args);
+    let (_, topics, data) = s.env.events().all().last().unwrap();
+    assert_eq!(topics, ("UpgradeEvent",).into_val(&s.env));
+    let new_wasm_hash: BytesN<32> = data.into_val(&s.env);
+    assert_eq!(new_wasm_hash, wasm_hash);
 }